@@ -1,3 +1,4 @@
+use crate::operations::generate::extraction::ALGORITHMS;
 use clap::{builder::PossibleValue, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 use std::path::PathBuf;
@@ -105,6 +106,14 @@ pub enum FlavoursCommand {
         #[arg(short, long)]
         stdin: bool,
 
+        /// Fail when a template uses variables that aren't part of the scheme
+        #[arg(long)]
+        strict: bool,
+
+        /// Generates the scheme from an image (such as a wallpaper) and applies it. If no image is given, uses the configured wallpaper
+        #[arg(long, num_args = 0..=1, conflicts_with_all = ["stdin", "pattern"])]
+        image: Option<Option<PathBuf>>,
+
         #[command(flatten)]
         pattern_arg: PatternArg,
 
@@ -122,19 +131,29 @@ pub enum FlavoursCommand {
 
         /// Subtemplate to use when building with user defined templates
         subtemplate: String,
+
+        /// Fail when the template uses variables that aren't part of the scheme
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Checks a template for unknown variables and malformed tags
+    CheckTemplate {
+        /// Template (optionally followed by /subtemplate) or template file to check
+        template: String,
     },
 
     /// Get information from the last applied scheme
     Current,
 
-    /// Generate schemes based on images
+    /// Generate schemes based on images or colors
     Generate {
         /// Scheme slug (the name you specify when applying schemes) to output to. If ommited, defaults to 'generated'
         #[arg(short, long, default_value = "generated")]
         slug: String,
 
-        /// In which luminance mode to generate the scheme
-        #[arg(short, long, default_value = "auto", value_parser = [PossibleValue::new("auto"), PossibleValue::new("dark"), PossibleValue::new("light")])]
+        /// In which luminance mode to generate the scheme. 'both' writes a dark and a light scheme, suffixing their slugs with '-dark' and '-light'
+        #[arg(short, long, default_value = "auto", value_parser = [PossibleValue::new("auto"), PossibleValue::new("dark"), PossibleValue::new("light"), PossibleValue::new("both")])]
         mode: Option<String>,
 
         /// Scheme display name (can include spaces and capitalization) to write, defaults to 'generated'
@@ -149,8 +168,60 @@ pub enum FlavoursCommand {
         #[arg(short, long)]
         stdout: bool,
 
-        /// Image file from where to generate scheme
-        image: PathBuf,
+        /// Algorithm used to extract the color palette from the image
+        #[arg(long, default_value = "median-cut", value_parser = ALGORITHMS)]
+        algorithm: String,
+
+        /// How many colors to extract from the image
+        #[arg(long, default_value = "15", value_parser = clap::value_parser!(u8).range(2..))]
+        palette_size: u8,
+
+        /// Sampling step when extracting colors (1 looks at every pixel, higher values are faster but less accurate)
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u8).range(1..=10))]
+        quality: u8,
+
+        /// Only use this region of the image(s), as x,y,width,height (in pixels)
+        #[arg(long)]
+        crop: Option<String>,
+
+        /// Ignore pixels less opaque than this (0 to 255)
+        #[arg(long, default_value = "125")]
+        alpha_threshold: u8,
+
+        /// Downscale images larger than this (on either side) before extracting colors. 0 disables downscaling
+        #[arg(long, default_value = "1024")]
+        max_dimension: u32,
+
+        /// Seed for randomized extraction algorithms (kmeans-lab). The same seed always gives the same scheme
+        #[arg(long, default_value = "0")]
+        seed: u64,
+
+        /// Minimum WCAG contrast ratio (1 to 21) of the foreground and accent colors against the background
+        #[arg(long, default_value = "4.5")]
+        min_contrast: f32,
+
+        /// Minimum WCAG contrast ratio (1 to 21) of comments (base03) against the background
+        #[arg(long, default_value = "3")]
+        min_comment_contrast: f32,
+
+        /// Generate scheme from a seed color (such as '#7aa2f7') instead of an image
+        #[arg(long, conflicts_with_all = ["images", "from_palette"])]
+        from_color: Option<String>,
+
+        /// Generate scheme from a comma separated list of colors instead of an image
+        #[arg(long, value_delimiter = ',', conflicts_with = "images")]
+        from_palette: Option<Vec<String>>,
+
+        /// Color harmony rule used to complete the colors given with --from-color or --from-palette
+        #[arg(long, default_value = "analogous", value_parser = ["analogous", "triadic", "complementary"])]
+        harmony: String,
+
+        #[command(flatten)]
+        output_arg: OutputArg,
+
+        /// Image file(s) from where to generate scheme. Colors from multiple images are blended into a single palette
+        #[arg(required_unless_present_any = ["from_color", "from_palette"])]
+        images: Vec<PathBuf>,
     },
 
     /// Shows scheme information
@@ -162,10 +233,63 @@ pub enum FlavoursCommand {
         output_arg: OutputArg,
     },
 
+    /// Shows installed sources, extra sources never updated and schemes overriding installed ones
+    Status {
+        #[command(flatten)]
+        output_arg: OutputArg,
+    },
+
     /// Downloads schemes, templates, or updates their lists (from repos specified in sources.yml)
     Update {
         ///  Downloads schemes, templates, or updates their lists (from repos specified in sources.yml)
-        #[arg(short, long, default_value = "all", value_parser = ["lists", "schemes", "templates", "all"])]
-        operation: String,
+        #[arg(value_parser = ["lists", "schemes", "templates", "all"])]
+        operation: Option<String>,
+
+        /// Only update schemes or templates with these names (or glob patterns, such as 'gruvbox*')
+        #[arg(requires = "operation")]
+        names: Vec<String>,
+
+        /// Same as the operation argument, kept for compatibility
+        #[arg(short = 'o', long = "operation", hide = true, conflicts_with = "operation", value_parser = ["lists", "schemes", "templates", "all"])]
+        operation_flag: Option<String>,
+
+        /// Remove schemes or templates that are no longer on their lists
+        #[arg(long)]
+        prune: bool,
+        /// Update every source to the revision on flavours.lock, instead of the latest one. Errors out if a source isn't on it, or fails to update
+        /// Update every source to the revision on flavours.lock, instead of the latest one
+        #[arg(long, conflicts_with = "bump")]
+        locked: bool,
+
+        /// Update only this source to its latest revision, keeping the others on the revision on flavours.lock. Prefix it with its kind (such as 'schemes/gruvbox') if there are several sources with that name
+        #[arg(long)]
+        bump: Option<String>,
+
+        /// How many sources to update at once
+        #[arg(short, long, default_value_t = 8)]
+        jobs: usize,
+
+        /// How many times (up to 10) to retry sources that fail to download
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(..=10))]
+        retries: u32,
+
+        /// Stop on the first source that fails to update, and exit with an error
+        #[arg(long)]
+        fail_fast: bool,
     },
 }
+
+impl FlavoursCommand {
+    /// Output format the command was asked for, if any
+    pub fn output(&self) -> Option<&Output> {
+        let output_arg = match self {
+            FlavoursCommand::List(ListCommand::Schemes { output_arg, .. })
+            | FlavoursCommand::List(ListCommand::Templates { output_arg, .. })
+            | FlavoursCommand::Generate { output_arg, .. }
+            | FlavoursCommand::Info { output_arg, .. }
+            | FlavoursCommand::Status { output_arg } => output_arg,
+            _ => return None,
+        };
+        output_arg.output.as_ref()
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 use std::path::Path;
 
@@ -14,6 +14,15 @@ pub struct Config {
 
     pub item: Option<Vec<ConfigItem>>,
     pub items: Option<Vec<ConfigItem>>,
+
+    pub wallpaper: Option<Wallpaper>,
+}
+
+/// Structure for configuration wallpaper, used to generate schemes with `apply --image`
+#[derive(Deserialize, Debug)]
+pub struct Wallpaper {
+    pub image: Option<String>,
+    pub hook: Option<String>,
 }
 
 /// Structure for configuration extra sources
@@ -27,16 +36,109 @@ pub struct ExtraSource {
 #[derive(Deserialize, Debug)]
 pub struct ConfigItem {
     pub file: String,
-    pub template: String,
+    pub template: Option<String>,
+    pub template_file: Option<String>,
+    pub template_inline: Option<String>,
     pub subtemplate: Option<String>,
     pub hook: Option<String>,
     pub rewrite: Option<bool>,
     pub lightweight: Option<bool>,
     pub start: Option<String>,
     pub end: Option<String>,
+    pub insert: Option<Insert>,
+    pub follow_symlinks: Option<bool>,
+    pub blocks: Option<Vec<ConfigBlock>>,
     pub light: Option<Box<ConfigItem>>,
 }
 
+/// Where to insert delimited blocks missing from a file
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Insert {
+    Append,
+    Prepend,
+}
+
+/// Structure for configuration apply item blocks, to inject several templates on one file
+#[derive(Deserialize, Debug)]
+pub struct ConfigBlock {
+    pub name: String,
+    pub template: Option<String>,
+    pub template_file: Option<String>,
+    pub template_inline: Option<String>,
+    pub subtemplate: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+/// Where an apply item gets its template from
+#[derive(Debug)]
+pub enum TemplateSource<'a> {
+    /// Template and subtemplate names, looked up on the config and data dirs
+    Named {
+        template: &'a str,
+        subtemplate: &'a str,
+    },
+    /// Path to a template file
+    File(&'a str),
+    /// Template written on the configuration itself
+    Inline(&'a str),
+}
+
+/// Get which template source was specified, making sure there's exactly one
+///
+/// * `owner` - What the template belongs to, used on error messages
+fn template_source<'a>(
+    owner: String,
+    template: &'a Option<String>,
+    template_file: &'a Option<String>,
+    template_inline: &'a Option<String>,
+    subtemplate: &'a Option<String>,
+) -> Result<TemplateSource<'a>> {
+    match (template, template_file, template_inline) {
+        (Some(template), None, None) => Ok(TemplateSource::Named {
+            template,
+            subtemplate: subtemplate.as_deref().unwrap_or("default"),
+        }),
+        (None, Some(file), None) => Ok(TemplateSource::File(file)),
+        (None, None, Some(inline)) => Ok(TemplateSource::Inline(inline)),
+        (None, None, None) => Err(anyhow!(
+            "{} has no template. Set one of 'template', 'template_file' or 'template_inline'.",
+            owner
+        )),
+        _ => Err(anyhow!(
+            "{} has more than one template. Set only one of 'template', 'template_file' or 'template_inline'.",
+            owner
+        )),
+    }
+}
+
+impl ConfigItem {
+    /// Get which template source this item uses, making sure exactly one was specified
+    pub fn template_source(&self) -> Result<TemplateSource<'_>> {
+        template_source(
+            format!("Item for {:?}", self.file),
+            &self.template,
+            &self.template_file,
+            &self.template_inline,
+            &self.subtemplate,
+        )
+    }
+}
+
+impl ConfigBlock {
+    /// Get which template source this block uses, making sure exactly one was specified
+    pub fn template_source(&self) -> Result<TemplateSource<'_>> {
+        template_source(
+            format!("Block {:?}", self.name),
+            &self.template,
+            &self.template_file,
+            &self.template_inline,
+            &self.subtemplate,
+        )
+    }
+}
+
 impl Config {
     /// Parse a TOML str into a Config struct
     pub fn read(contents: &str, path: &Path) -> Result<Config> {
@@ -2,12 +2,12 @@ use anyhow::{anyhow, Context, Result};
 use base16_color_scheme::scheme::BaseIndex;
 use base16_color_scheme::scheme::RgbColor;
 use base16_color_scheme::Scheme;
-use glob::glob;
+use glob::{glob, glob_with, MatchOptions};
 use path::{Path, PathBuf};
 use std::path;
 use std::{fs, vec};
 
-use crate::scheme::Luminance;
+use crate::scheme::{self, Luminance};
 
 /// Find color schemes matching pattern in either the config dir or the data dir.
 ///
@@ -23,15 +23,24 @@ pub fn find_schemes(pattern: &str, base_dir: &Path, config_dir: &Path) -> Result
 
     let mut found = Vec::new();
     for dir in dirs {
-        let glob_pattern = format!("{}/*/{}.y*ml", dir, pattern);
-        let matches = glob(&glob_pattern)?;
+        // Schemes can be nested, such as on scheme collections
+        let glob_pattern = format!("{}/**/{}.y*ml", dir, pattern);
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            ..MatchOptions::new()
+        };
+        let matches = glob_with(&glob_pattern, options)?;
         for element in matches {
             // Check if scheme is valid
             let current_element = element?;
+            // '**' can match the same file more than once
+            if found.contains(&current_element) {
+                continue;
+            }
             let scheme_contents = fs::read_to_string(&current_element)
                 .with_context(|| format!("Couldn't read scheme file at {:?}.", current_element))?;
 
-            let scheme_result: Result<Scheme, _> = serde_yaml::from_str(&scheme_contents);
+            let scheme_result = scheme::parse(&scheme_contents);
             match scheme_result {
                 Ok(_) => {
                     found.push(current_element);
@@ -48,14 +57,19 @@ pub fn find_schemes(pattern: &str, base_dir: &Path, config_dir: &Path) -> Result
     Ok(found)
 }
 
+/// Get the perceived luminance of a color, from 0 to 1
+///
+/// * `red`, `green`, `blue` - Color components, from 0 to 1
+pub fn color_luminance(red: f32, green: f32, blue: f32) -> f32 {
+    // there are exacter ways, this turns out to be good enough
+    // https://www.w3.org/TR/AERT/#color-contrast
+    0.299 * red + 0.587 * green + 0.114 * blue
+}
+
 pub fn get_luminance(scheme: &Scheme) -> Luminance {
     let rgb2luminance = |rgb: &RgbColor| {
         let [r, g, b] = rgb.0;
-
-        // there are exacter ways, this turns out to be good enough
-        // https://www.w3.org/TR/AERT/#color-contrast
-        let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
-        luminance / 255.0
+        color_luminance(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
     };
 
     // Take into account the main background colors as per the styling guide
@@ -83,7 +97,7 @@ pub fn filter_schemes_by_theme(schemes: Vec<PathBuf>, theme: &str) -> Result<Vec
         let scheme_contents = fs::read_to_string(&scheme_file)
             .with_context(|| format!("Couldn't read scheme file at {:?}.", scheme_file))?;
 
-        let scheme: Scheme = serde_yaml::from_str(&scheme_contents)?;
+        let scheme = scheme::parse(&scheme_contents)?;
 
         let light_mode = match get_luminance(&scheme) {
             Luminance::Light => true,
@@ -110,7 +124,7 @@ pub fn filter_schemes_by_theme(schemes: Vec<PathBuf>, theme: &str) -> Result<Vec
 pub fn find_templates(pattern: &str, base_dir: &Path, config_dir: &Path) -> Result<Vec<PathBuf>> {
     let config_scheme_dir = config_dir.join("templates");
     let data_scheme_dir = base_dir.join("base16").join("templates");
-    let dir_vec = vec![config_scheme_dir, data_scheme_dir];
+    let dir_vec = [config_scheme_dir, data_scheme_dir];
     let dir_vec: Vec<&str> = dir_vec.iter().filter_map(|dir| dir.to_str()).collect();
     let pattern = match pattern
         // remove extension if it was included
@@ -173,10 +187,10 @@ pub fn find_template(
     } else if template_data_file.is_file() {
         Ok(template_data_file)
     } else {
-        return Err(anyhow!(
+        Err(anyhow!(
             "Neither {:?} or {:?} exist",
             template_config_file,
             template_data_file
-        ));
+        ))
     }
 }
@@ -2,5 +2,7 @@ pub mod cli;
 pub mod completions;
 pub mod config;
 pub mod find;
+pub mod lock;
 pub mod operations;
 pub mod scheme;
+pub mod sources;
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Lockfile name, it lives next to the configuration file
+pub const LOCKFILE: &str = "flavours.lock";
+
+/// Structure for the lockfile, pinning each source to a revision
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub lists: BTreeMap<String, LockedSource>,
+    #[serde(default)]
+    pub schemes: BTreeMap<String, LockedSource>,
+    #[serde(default)]
+    pub templates: BTreeMap<String, LockedSource>,
+}
+
+/// Structure for a locked source
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedSource {
+    /// Where the source was fetched from
+    pub source: String,
+    /// Commit the source resolved to
+    pub rev: String,
+}
+
+impl Lockfile {
+    /// Get the lockfile path, from the configuration file path
+    pub fn path(config_path: &Path) -> PathBuf {
+        config_path.with_file_name(LOCKFILE)
+    }
+
+    /// Read the lockfile, or get an empty one if it doesn't exist
+    pub fn read(path: &Path) -> Result<Lockfile> {
+        if !path.exists() {
+            return Ok(Lockfile::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read lockfile {:?}", path))?;
+        toml::from_str(&contents).with_context(|| {
+            format!(
+                "Couldn't parse lockfile ({:?}). Check if it's syntatically correct",
+                path
+            )
+        })
+    }
+
+    /// Write the lockfile
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = format!(
+            "# This file is generated by 'flavours update', pinning each source to a revision.\n\
+             # Use 'flavours update --locked' to get exactly these revisions.\n\n{}",
+            toml::to_string(self)?
+        );
+        fs::write(path, contents).with_context(|| format!("Couldn't write lockfile {:?}", path))
+    }
+}
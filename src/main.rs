@@ -1,19 +1,12 @@
 use anyhow::{anyhow, Context, Result};
-use base16_color_scheme::{
-    scheme::{BaseIndex, RgbColor},
-    Scheme,
-};
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
 use dirs::{data_dir, preference_dir};
 use flavours::cli::{Flavours, FlavoursCommand};
 use flavours::operations::current::get_scheme;
-use flavours::operations::{apply, build, current, generate, update};
+use flavours::operations::{apply, build, check, current, generate, status, update};
 use flavours::{cli::Output, find::find_template};
 use flavours::{find::find_schemes, operations::list};
-use palette::Srgb;
-use std::collections::BTreeMap;
-use std::convert::TryInto;
 use std::env;
 use std::fs;
 use std::io;
@@ -75,8 +68,8 @@ fn main() -> Result<()> {
         }
     };
 
-    // Should we be verbose?
-    let verbose = matches.verbose;
+    // Should we be verbose? Not if printing JSON, verbose output would get mixed with it
+    let verbose = matches.verbose && matches.commands.output().is_none();
 
     if verbose {
         println!("Using directory: {:?}", flavours_dir);
@@ -112,6 +105,8 @@ fn main() -> Result<()> {
             lightweight,
             luminance_arg,
             stdin,
+            strict,
+            image,
         } => {
             //Get search patterns
             let patterns = match pattern_arg.pattern {
@@ -131,6 +126,8 @@ fn main() -> Result<()> {
                 &flavours_config,
                 lightweight,
                 stdin,
+                image.as_ref().map(|image| image.as_deref()),
+                strict,
                 verbose,
             )
         }
@@ -139,6 +136,7 @@ fn main() -> Result<()> {
             scheme,
             template,
             subtemplate,
+            strict,
         } => {
             let scheme_file_path = if Path::new(&scheme).exists() {
                 PathBuf::from(scheme)
@@ -189,7 +187,35 @@ fn main() -> Result<()> {
                     format!("Couldn't read template file at {:?}.", template_file_path)
                 })?;
 
-            build::build(scheme_slug, scheme_contents, template_contents)
+            build::build(scheme_slug, scheme_contents, template_contents, strict)
+        }
+
+        FlavoursCommand::CheckTemplate { template } => {
+            let template_file_path = if Path::new(&template).exists() {
+                PathBuf::from(&template)
+            } else {
+                // Subtemplate defaults to 'default' if not specified
+                let (template, subtemplate) =
+                    template.split_once('/').unwrap_or((&template, "default"));
+
+                find_template(template, subtemplate, &flavours_dir, &flavours_config_dir)
+                    .with_context(|| {
+                        format!(
+                            "Failed to locate subtemplate file {}/{}",
+                            template, subtemplate
+                        )
+                    })?
+            };
+
+            if verbose {
+                println!("Template is at: {:#?}", template_file_path);
+            }
+
+            let template_contents = fs::read_to_string(&template_file_path).with_context(|| {
+                format!("Couldn't read template file at {:?}.", template_file_path)
+            })?;
+
+            check::check_template(&template, &template_contents, verbose)
         }
 
         FlavoursCommand::List(list_matches) => {
@@ -286,8 +312,53 @@ fn main() -> Result<()> {
                 }
             }
         }
-        FlavoursCommand::Update { operation } => {
-            update::update(&operation, &flavours_dir, verbose, &flavours_config)
+        FlavoursCommand::Status { output_arg } => {
+            let status = status::status(&flavours_dir, &flavours_config_dir, &flavours_config)?;
+            if let Some(output_arg) = output_arg.output {
+                match output_arg {
+                    Output::Json => {
+                        let json_string = serde_json::to_string(&status)?;
+                        println!("{}", json_string);
+                    }
+                }
+            } else {
+                status.print();
+            }
+
+            Ok(())
+        }
+
+        FlavoursCommand::Update {
+            operation,
+            names,
+            operation_flag,
+            prune,
+            locked,
+            bump,
+            jobs,
+            retries,
+            fail_fast,
+        } => {
+            let pinning = match (locked, &bump) {
+                (true, _) => update::Pinning::Locked,
+                (false, Some(name)) => update::Pinning::Bump(name),
+                (false, None) => update::Pinning::Latest,
+            };
+            let operation = operation.or(operation_flag);
+            update::update(
+                operation.as_deref().unwrap_or("all"),
+                &names,
+                &flavours_dir,
+                verbose,
+                &flavours_config,
+                pinning,
+                &update::Options {
+                    jobs,
+                    retries,
+                    fail_fast,
+                    prune,
+                },
+            )
         }
 
         FlavoursCommand::Info { scheme, output_arg } => {
@@ -322,92 +393,173 @@ fn main() -> Result<()> {
             name,
             author,
             stdout,
-            image,
+            images,
             mode,
+            algorithm,
+            palette_size,
+            quality,
+            crop,
+            alpha_threshold,
+            max_dimension,
+            seed,
+            min_contrast,
+            min_comment_contrast,
+            output_arg,
+            from_color,
+            from_palette,
+            harmony,
         } => {
-            let image_file = image
-                .canonicalize()
-                .with_context(|| "Image file invalid or not found")?;
-
-            let mode = match mode.as_deref() {
-                Some("dark") => Ok(generate::Mode::Dark),
-                Some("light") => Ok(generate::Mode::Light),
-                Some("auto") => {
-                    let img_buffer = image::open(image_file)?;
-                    let img_pixels = img_buffer.to_rgba8().into_raw();
-
-                    // Use color thief to get a palette
-                    let palette = color_thief::get_palette(
-                        img_pixels.as_slice(),
-                        color_thief::ColorFormat::Rgba,
-                        1,
-                        15,
-                    )?;
-
-                    // Calculate the average luminance of the colors in the palette
-                    let total_luminance: f32 = palette
-                        .iter()
-                        .map(|&color| {
-                            let srgb_color: Srgb = Srgb::new(
-                                f32::from(color.r) / 255.0,
-                                f32::from(color.g) / 255.0,
-                                f32::from(color.b) / 255.0,
-                            );
-
-                            let red = srgb_color.red as f32 * 0.222;
-                            let green = srgb_color.green as f32 * 0.707;
-                            let blue = srgb_color.blue as f32 * 0.071;
+            let image_files = images
+                .iter()
+                .map(|image| {
+                    image
+                        .canonicalize()
+                        .with_context(|| format!("Image file {:?} invalid or not found", image))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let image_options = generate::ImageOptions {
+                crop: crop.as_deref().map(generate::parse_crop).transpose()?,
+                alpha_threshold,
+                max_dimension: match max_dimension {
+                    0 => None,
+                    max_dimension => Some(max_dimension),
+                },
+            };
 
-                            red + green + blue
-                        })
-                        .sum();
+            if !(1.0..=21.0).contains(&min_contrast)
+                || !(1.0..=21.0).contains(&min_comment_contrast)
+            {
+                return Err(anyhow!("Contrast ratios must be between 1 and 21"));
+            }
+            let min_contrast = generate::MinContrast {
+                text: min_contrast,
+                comments: min_comment_contrast,
+            };
 
-                    let average_luminance = total_luminance / palette.len() as f32;
+            // Colors to generate from, if not using an image
+            let seeds = match (from_color, from_palette) {
+                (Some(color), _) => Some(vec![generate::parse_color(&color)?]),
+                (None, Some(colors)) => Some(
+                    colors
+                        .iter()
+                        .map(|color| generate::parse_color(color))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                (None, None) => None,
+            };
 
-                    if verbose {
-                        println!("The average luminance is: {}", average_luminance);
-                    };
+            let harmony = match harmony.as_str() {
+                "analogous" => Ok(generate::Harmony::Analogous),
+                "triadic" => Ok(generate::Harmony::Triadic),
+                "complementary" => Ok(generate::Harmony::Complementary),
+                _ => Err(anyhow!("No valid harmony specified")),
+            }?;
 
-                    if average_luminance > 0.5 {
-                        Ok(generate::Mode::Light)
-                    } else {
-                        Ok(generate::Mode::Dark)
-                    }
-                }
+            // Modes to generate, and the slug suffix for each (none if it should be detected)
+            let mode_name = mode.clone().unwrap_or_else(|| "auto".into());
+            let modes = match mode.as_deref() {
+                Some("dark") => Ok(Some(vec![(generate::Mode::Dark, None)])),
+                Some("light") => Ok(Some(vec![(generate::Mode::Light, None)])),
+                Some("both") => Ok(Some(vec![
+                    (generate::Mode::Dark, Some("dark")),
+                    (generate::Mode::Light, Some("light")),
+                ])),
+                Some("auto") => Ok(None),
                 _ => Err(anyhow!("No valid mode specified")),
             }?;
 
-            let colors = generate::generate(&image, mode, verbose)?;
-
-            let scheme = Scheme {
-                scheme: name,
-                slug,
-                author,
-                colors: colors
-                    .into_iter()
-                    .enumerate()
-                    .map(|(index, color)| {
-                        let mut rgb_color = [0u8; 3];
-                        hex::decode_to_slice(color, &mut rgb_color)?;
-                        Ok((BaseIndex(index.try_into()?), RgbColor(rgb_color)))
-                    })
-                    .collect::<Result<BTreeMap<_, _>>>()?,
+            // Get the palette once, and use it for every mode
+            let palette = match &seeds {
+                Some(seeds) => generate::harmony_palette(seeds, &harmony),
+                None if !image_files.is_empty() => {
+                    let extractor = generate::extraction::extractor(&algorithm, seed)?;
+                    generate::extract_palette(
+                        &image_files,
+                        &image_options,
+                        extractor.as_ref(),
+                        palette_size,
+                        quality,
+                    )?
+                }
+                None => return Err(anyhow!("No image or colors specified")),
             };
 
-            if stdout {
-                print!("{}", serde_yaml::to_string(&scheme)?);
-            } else {
-                let path = flavours_dir
-                    .join("base16")
-                    .join("schemes")
-                    .join("generated");
-                if !path.exists() {
-                    create_dir_all(&path)
-                        .with_context(|| format!("Couldn't create directory {:?}", &path))?;
+            // Auto mode, detect from the seed colors or extracted palette
+            let modes = match modes {
+                Some(modes) => modes,
+                None => {
+                    let mode =
+                        generate::detect_mode(seeds.as_deref().unwrap_or(&palette), verbose)?;
+                    vec![(mode, None)]
+                }
+            };
+
+            let report_output = output_arg.output;
+
+            let mut reports = Vec::new();
+            for (mode, suffix) in modes {
+                let report = generate::generate_report(&palette, mode, &min_contrast, verbose)?;
+
+                let scheme = generate::build_scheme(
+                    report.colors.values().cloned().collect(),
+                    match suffix {
+                        Some(suffix) => format!("{} ({})", name, suffix),
+                        None => name.clone(),
+                    },
+                    match suffix {
+                        Some(suffix) => format!("{}-{}", slug, suffix),
+                        None => slug.clone(),
+                    },
+                    author.clone(),
+                )?;
+
+                // Record where the scheme came from, if it was a single image
+                let header = match (&seeds, &image_files[..]) {
+                    (None, [image_file]) => {
+                        generate::Provenance::from_image(image_file, suffix.unwrap_or(&mode_name))?
+                            .header()
+                    }
+                    _ => String::new(),
+                };
+
+                if report_output.is_some() {
+                    let mut report_json = serde_json::to_value(&report)?;
+                    report_json["slug"] = serde_json::json!(scheme.slug);
+                    reports.push(report_json);
                 }
-                let file_path = &path.join(format!("{}.yaml", &scheme.slug));
-                write(file_path, serde_yaml::to_string(&scheme)?)
+
+                if stdout {
+                    // The report is printed instead
+                    if report_output.is_some() {
+                        continue;
+                    }
+                    // Separate documents when printing more than one scheme
+                    if suffix.is_some() {
+                        println!("---");
+                    }
+                    print!("{}{}", header, serde_yaml::to_string(&scheme)?);
+                } else {
+                    let path = flavours_dir
+                        .join("base16")
+                        .join("schemes")
+                        .join("generated");
+                    if !path.exists() {
+                        create_dir_all(&path)
+                            .with_context(|| format!("Couldn't create directory {:?}", &path))?;
+                    }
+                    let file_path = &path.join(format!("{}.yaml", &scheme.slug));
+                    write(
+                        file_path,
+                        format!("{}{}", header, serde_yaml::to_string(&scheme)?),
+                    )
                     .with_context(|| format!("Couldn't write scheme file at {:?}", path))?;
+                }
+            }
+
+            if let Some(Output::Json) = report_output {
+                let json_object = serde_json::json!({ "schemes": reports });
+                println!("{}", serde_json::to_string(&json_object)?);
             }
             Ok(())
         }
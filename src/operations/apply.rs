@@ -8,10 +8,11 @@ use std::process;
 use std::str;
 use std::thread;
 
-use crate::config::Config;
+use crate::config::{Config, Insert, TemplateSource};
 use crate::find::{filter_schemes_by_theme, find_schemes, find_template, get_luminance};
 use crate::operations::build::build_template;
-use crate::scheme::Luminance;
+use crate::operations::generate;
+use crate::scheme::{self, Luminance};
 
 /// Picks a random path, from given vec
 /// * `values` - Vec with paths
@@ -55,9 +56,123 @@ fn run_hook(command: Option<String>, shell: &str, verbose: bool) -> Result<()> {
     Ok(())
 }
 
+/// Quote an argument for a hook command
+///
+/// The argument is quoted for the shell, and then escaped for the `shell` wrapper, which might
+/// already put the command inside single or double quotes (such as the default `sh -c '{}'`)
+///
+/// * `argument` - Argument to quote
+/// * `shell` - Shell the hook is run through
+fn hook_argument(argument: &str, shell: &str) -> String {
+    let quoted = shell_words::quote(argument);
+    if shell.contains("'{}'") {
+        quoted.replace('\'', "'\\''")
+    } else if shell.contains("\"{}\"") {
+        let mut escaped = String::with_capacity(quoted.len());
+        for character in quoted.chars() {
+            if matches!(character, '"' | '\\' | '$' | '`') {
+                escaped.push('\\');
+            }
+            escaped.push(character);
+        }
+        escaped
+    } else {
+        quoted.into_owned()
+    }
+}
+
+/// Check if a delimiter line is present
+///
+/// Delimiters are case-insensitive and ignore surrounding whitespace
+///
+/// * `file_content` - String to look for the line in
+/// * `delimiter` - Delimiter line to look for
+fn contains_line(file_content: &str, delimiter: &str) -> bool {
+    let delimiter = delimiter.trim().to_lowercase();
+    file_content
+        .lines()
+        .any(|line| line.trim().to_lowercase() == delimiter)
+}
+
+/// Get comment syntax for a file, based on its name
+///
+/// Returns what comments start and end with (the latter being empty for line comments)
+///
+/// * `file` - Path to the file
+fn comment_style(file: &str) -> (&'static str, &'static str) {
+    let path = path::Path::new(file);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match (file_name, extension.as_str()) {
+        ("vimrc" | ".vimrc" | "gvimrc" | ".gvimrc" | ".exrc", _) | (_, "vim") => ("\"", ""),
+        (_, "lua" | "hs" | "elm" | "sql") => ("--", ""),
+        (_, "xml" | "html" | "htm" | "svg" | "xaml" | "xbel" | "ui" | "md") => ("<!--", "-->"),
+        (_, "css") => ("/*", "*/"),
+        (
+            _,
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "json5" | "jsonc" | "scss" | "less"
+            | "rasi" | "qml" | "c" | "h" | "cpp" | "hpp" | "rs" | "go" | "java" | "kt" | "swift"
+            | "dart" | "zig",
+        ) => ("//", ""),
+        _ => ("#", ""),
+    }
+}
+
+/// Get start and end delimiters for a block
+///
+/// When not specified, they default to a comment according to the file type. Files already using
+/// the `#` delimiters keep working regardless of their type.
+///
+/// * `file` - Path to the file
+/// * `file_content` - Current file contents
+/// * `start` - Configured start delimiter, if any
+/// * `end` - Configured end delimiter, if any
+/// * `name` - Block name, if any
+fn delimiters(
+    file: &str,
+    file_content: &str,
+    start: &Option<String>,
+    end: &Option<String>,
+    name: Option<&str>,
+) -> (String, String) {
+    let delimiter = |(prefix, suffix): (&str, &str), word: &str| {
+        let text = match name {
+            Some(name) => format!("{} flavours:{}", word, name),
+            None => format!("{} flavours", word),
+        };
+        if suffix.is_empty() {
+            format!("{} {}", prefix, text)
+        } else {
+            format!("{} {} {}", prefix, text, suffix)
+        }
+    };
+
+    let legacy_style = ("#", "");
+    let mut style = comment_style(file);
+    if !contains_line(file_content, &delimiter(style, "Start"))
+        && contains_line(file_content, &delimiter(legacy_style, "Start"))
+    {
+        style = legacy_style;
+    }
+
+    (
+        start.clone().unwrap_or_else(|| delimiter(style, "Start")),
+        end.clone().unwrap_or_else(|| delimiter(style, "End")),
+    )
+}
+
 /// Replace with delimiter lines
 ///
-/// In a string, removes everything from one line to another, and puts the built template in place
+/// In a string, removes everything between every pair of start and end lines, and puts the built
+/// template in place
 ///
 /// * `file_content` - String with lines to be replaced
 /// * `start` - Where to start replacing
@@ -69,39 +184,198 @@ fn replace_delimiter(
     end: &str,
     built_template: &str,
 ) -> Result<String> {
+    let start = start.trim().to_lowercase();
+    let end = end.trim().to_lowercase();
+
     let mut changed_content = String::new();
 
     let mut found_start = false;
-    let mut found_end = false;
-
-    let mut appended = false;
+    let mut inside = false;
 
     for line in file_content.lines() {
-        if found_start && !found_end {
-            if !appended {
-                changed_content.push_str(&format!("{}\n", built_template));
-                appended = true;
-            }
+        if inside {
             if line.trim().to_lowercase().eq(&end) {
                 changed_content.push_str(&format!("{}\n", line));
-                found_end = true;
+                inside = false;
             }
         } else {
             changed_content.push_str(&format!("{}\n", line));
             if line.trim().to_lowercase().eq(&start) {
-                found_start = true
+                changed_content.push_str(&format!("{}\n", built_template));
+                found_start = true;
+                inside = true;
             }
         }
     }
     if !found_start {
         Err(anyhow!("Couldn't find starting string."))
-    } else if !found_end {
+    } else if inside {
         Err(anyhow!("Couldn't find ending string."))
     } else {
         Ok(changed_content)
     }
 }
 
+/// Insert a delimited block
+///
+/// * `file_content` - String to insert the block in
+/// * `insert` - Whether to append or prepend the block
+/// * `start` - Start delimiter
+/// * `end` - End delimiter
+/// * `built_template` - Built template to be injected
+fn insert_block(
+    file_content: &str,
+    insert: Insert,
+    start: &str,
+    end: &str,
+    built_template: &str,
+) -> String {
+    let block = format!("{}\n{}\n{}\n", start.trim(), built_template, end.trim());
+    match insert {
+        Insert::Append => {
+            let mut changed_content = String::from(file_content);
+            if !changed_content.is_empty() && !changed_content.ends_with('\n') {
+                changed_content.push('\n');
+            }
+            changed_content.push_str(&block);
+            changed_content
+        }
+        Insert::Prepend => format!("{}{}", block, file_content),
+    }
+}
+
+/// Read a target file to replace blocks in
+///
+/// * `file` - Path to the file
+/// * `insert` - Where missing blocks are inserted, if they are
+fn read_target(file: &str, insert: Option<Insert>) -> Result<String> {
+    match fs::read_to_string(file) {
+        Ok(content) => Ok(content),
+        // Missing files are fine if we're going to insert the blocks anyway
+        Err(error) if error.kind() == io::ErrorKind::NotFound && insert.is_some() => {
+            Ok(String::new())
+        }
+        Err(error) => Err(error).with_context(|| format!("Couldn't read file {:?}", file)),
+    }
+}
+
+/// Write to a target file
+///
+/// Existing files are written in place, so their mode and ownership are kept. Symlinks are either
+/// followed to the real file, or replaced by a regular file (with the same mode as their target,
+/// but owned by whoever runs flavours). Following a dangling symlink is an error, but replacing
+/// one isn't, as its target is never used.
+///
+/// * `file` - Path to write to
+/// * `contents` - What to write
+/// * `follow_symlinks` - Write to the symlink target, instead of replacing the symlink
+fn write_file(file: &str, contents: &str, follow_symlinks: bool) -> Result<()> {
+    let file_path = path::Path::new(file);
+    let is_symlink = fs::symlink_metadata(file_path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false);
+
+    if is_symlink && follow_symlinks {
+        // Don't create files through dangling symlinks
+        let target = file_path.canonicalize().with_context(|| {
+            format!(
+                "Couldn't write to file {:?}. It is a symlink to a file that doesn't exist.",
+                file
+            )
+        })?;
+        fs::write(&target, contents).with_context(|| {
+            format!(
+                "Couldn't write to file {:?} (linked by {:?}).",
+                target, file
+            )
+        })
+    } else if is_symlink {
+        // Write next to the symlink and move the file over it, so it's kept if anything fails
+        let permissions = fs::metadata(file_path)
+            .ok()
+            .map(|metadata| metadata.permissions());
+        let name = file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temporary = file_path.with_file_name(format!(".{}.flavours-{}", name, process::id()));
+        let written = fs::write(&temporary, contents)
+            .with_context(|| format!("Couldn't write to file {:?}.", temporary))
+            .and_then(|_| match permissions {
+                Some(permissions) => fs::set_permissions(&temporary, permissions)
+                    .with_context(|| format!("Couldn't set permissions of file {:?}.", temporary)),
+                None => Ok(()),
+            })
+            .and_then(|_| {
+                fs::rename(&temporary, file_path)
+                    .with_context(|| format!("Couldn't replace symlink {:?}.", file))
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        written
+    } else {
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create directory {:?}.", parent))?;
+        }
+        fs::write(file_path, contents)
+            .with_context(|| format!("Couldn't write to file {:?}.", file))
+    }
+}
+
+/// Read an item's template
+///
+/// Returns a name describing the template (for messages) and its contents
+///
+/// * `source` - Where to get the template from
+/// * `scheme` - Scheme being applied, used for `{scheme}` subtemplates
+/// * `base_dir` - Flavours base directory
+/// * `config_dir` - Flavours configuration directory
+fn read_template(
+    source: TemplateSource,
+    scheme: &Scheme,
+    base_dir: &path::Path,
+    config_dir: &path::Path,
+) -> Result<(String, String)> {
+    match source {
+        TemplateSource::Named {
+            template,
+            subtemplate,
+        } => {
+            let subtemplate = if subtemplate == "{scheme}" {
+                match find_template(template, &scheme.scheme, base_dir, config_dir) {
+                    Ok(_value) => scheme.scheme.as_str(),
+                    Err(_e) => "default",
+                }
+            } else {
+                subtemplate
+            };
+
+            let subtemplate_file = find_template(template, subtemplate, base_dir, config_dir)
+                .with_context(|| {
+                    format!(
+                        "Failed to locate subtemplate file {}/{}",
+                        template, subtemplate,
+                    )
+                })?;
+
+            let template_content = fs::read_to_string(&subtemplate_file)
+                .with_context(||format!("Couldn't read template {}/{} at {:?}. Check if the correct template/subtemplate was specified, and run the update templates command if you didn't already.", template, subtemplate, subtemplate_file))?;
+
+            Ok((format!("{}/{}", template, subtemplate), template_content))
+        }
+        TemplateSource::File(file) => {
+            let file = shellexpand::full(file)?.to_string();
+            let template_content = fs::read_to_string(&file)
+                .with_context(|| format!("Couldn't read template file {:?}.", file))?;
+
+            Ok((file, template_content))
+        }
+        TemplateSource::Inline(content) => Ok((String::from("inline template"), content.into())),
+    }
+}
+
 /// Apply function
 ///
 /// * `patterns` - Which patterns the user specified
@@ -109,7 +383,10 @@ fn replace_delimiter(
 /// * `config_path` - Flavours configuration path
 /// * `light` - Don't run hooks marked as non-lightweight
 /// * `from_stdin` - Read scheme from stdin?
+/// * `image` - Generate scheme from an image? If it's `Some(None)`, use the configured wallpaper
+/// * `strict` - Error out on template variables that aren't part of the scheme
 /// * `verbose` - Should we be verbose?
+#[allow(clippy::too_many_arguments)]
 pub fn apply(
     patterns: Vec<&str>,
     theme: &str,
@@ -118,9 +395,72 @@ pub fn apply(
     config_path: &path::Path,
     lightweight_mode: bool,
     from_stdin: bool,
+    image: Option<Option<&path::Path>>,
+    strict: bool,
     verbose: bool,
 ) -> Result<()> {
-    let (scheme_contents, scheme_slug) = if from_stdin {
+    //Check if config file exists
+    if !config_path.exists() {
+        eprintln!("Config {:?} doesn't exist, creating", config_path);
+        let default_content =
+            fs::read_to_string(path::Path::new("/etc/flavours.conf")).unwrap_or_default();
+        let config_path_parent = config_path
+            .parent()
+            .with_context(|| format!("Couldn't get parent directory of {:?}", config_path))?;
+
+        fs::create_dir_all(config_path_parent).with_context(|| {
+            format!(
+                "Couldn't create configuration file parent directory {:?}",
+                config_path_parent
+            )
+        })?;
+        fs::write(config_path, default_content)
+            .with_context(|| format!("Couldn't create configuration file at {:?}", config_path))?;
+    }
+
+    let config_contents = fs::read_to_string(config_path)
+        .with_context(|| format!("Couldn't read configuration file {:?}.", config_path))?;
+
+    let config = Config::read(&config_contents, config_path)?;
+
+    //Image to generate the scheme from, if any
+    let image = match image {
+        Some(Some(image)) => Some(image.to_path_buf()),
+        Some(None) => {
+            let configured = config
+                .wallpaper
+                .as_ref()
+                .and_then(|wallpaper| wallpaper.image.as_ref())
+                .ok_or_else(|| {
+                    anyhow!("No image specified, and no 'image' set on the configuration's 'wallpaper'.")
+                })?;
+            Some(path::PathBuf::from(shellexpand::full(configured)?.to_string()))
+        }
+        None => None,
+    }
+    .map(|image| {
+        image
+            .canonicalize()
+            .with_context(|| format!("Image file {:?} invalid or not found", image))
+    })
+    .transpose()?;
+
+    let (scheme_contents, scheme_slug) = if let Some(image) = &image {
+        //Generate scheme, or reuse it if it was already generated from this image
+        let mode = match theme {
+            "dark" | "light" => theme,
+            _ => "auto",
+        };
+        let scheme_file = base_dir
+            .join("base16")
+            .join("schemes")
+            .join("generated")
+            .join("wallpaper.yaml");
+        (
+            generate::generate_cached(image, mode, "wallpaper", &scheme_file, verbose)?,
+            String::from("wallpaper"),
+        )
+    } else if from_stdin {
         let mut buffer = String::new();
         let stdin = io::stdin();
         let mut handle = stdin.lock();
@@ -170,7 +510,7 @@ pub fn apply(
         )
     };
     // This is NOT supplied by flavours.
-    let mut scheme: Scheme = serde_yaml::from_str(&scheme_contents)?;
+    let mut scheme = scheme::parse(&scheme_contents)?;
     scheme.slug = scheme_slug;
 
     let light_mode = match get_luminance(&scheme) {
@@ -185,32 +525,6 @@ pub fn apply(
         );
     }
 
-    //Check if config file exists
-    if !config_path.exists() {
-        eprintln!("Config {:?} doesn't exist, creating", config_path);
-        let default_content = match fs::read_to_string(path::Path::new("/etc/flavours.conf")) {
-            Ok(content) => content,
-            Err(_) => String::from(""),
-        };
-        let config_path_parent = config_path
-            .parent()
-            .with_context(|| format!("Couldn't get parent directory of {:?}", config_path))?;
-
-        fs::create_dir_all(config_path_parent).with_context(|| {
-            format!(
-                "Couldn't create configuration file parent directory {:?}",
-                config_path_parent
-            )
-        })?;
-        fs::write(config_path, default_content)
-            .with_context(|| format!("Couldn't create configuration file at {:?}", config_path))?;
-    }
-
-    let config_contents = fs::read_to_string(config_path)
-        .with_context(|| format!("Couldn't read configuration file {:?}.", config_path))?;
-
-    let config = Config::read(&config_contents, config_path)?;
-
     // If shell is present, check if it contains the placeholder
     let shell = config.shell.unwrap_or_else(|| "sh -c '{}'".into());
 
@@ -225,29 +539,13 @@ pub fn apply(
     //Iterate configurated entries (templates)
     let items_legacy = config.item.unwrap_or_default();
     let mut items = config.items.unwrap_or_default();
-    items.extend(items_legacy.into_iter());
+    items.extend(items_legacy);
 
     if items.is_empty() {
         return Err(anyhow!("Couldn't get items from config file. Check the default file or github for config examples."));
     }
 
     for item in items.iter() {
-        //Template name
-        let template = &item.template;
-        //Subtemplate name
-        let mut subtemplate = match &item.subtemplate {
-            Some(value) => String::from(value),
-            None => String::from("default"),
-        };
-
-        if subtemplate == "{scheme}" {
-            let subtemplate_scheme = find_template(template, &scheme.scheme, base_dir, config_dir);
-            subtemplate = match subtemplate_scheme {
-                Ok(_value) => scheme.scheme.clone(),
-                Err(_e) => String::from("default"),
-            }
-        };
-
         //Is the hook lightweight?
         let lightweight = match &item.lightweight {
             Some(value) => *value,
@@ -260,63 +558,101 @@ pub fn apply(
             None => false,
         };
 
-        //Replace start delimiter
-        let start = match &item.start {
-            Some(value) => String::from(value),
-            None => String::from("# Start flavours"),
-        }
-        .trim()
-        .to_lowercase();
-
-        //Replace end delimiter
-        let end = match &item.end {
-            Some(value) => String::from(value),
-            None => String::from("# End flavours"),
+        //Templates to inject, with their (optional) delimiters and block names
+        let mut sources = Vec::new();
+        match &item.blocks {
+            Some(blocks) => {
+                if item.template.is_some()
+                    || item.template_file.is_some()
+                    || item.template_inline.is_some()
+                {
+                    return Err(anyhow!(
+                        "Item for {:?} has blocks, so each block should specify its own template.",
+                        item.file
+                    ));
+                }
+                if rewrite {
+                    return Err(anyhow!(
+                        "Item for {:?} can't use both 'blocks' and 'rewrite'.",
+                        item.file
+                    ));
+                }
+                for block in blocks {
+                    sources.push((
+                        block.template_source()?,
+                        &block.start,
+                        &block.end,
+                        Some(block.name.as_str()),
+                    ));
+                }
+            }
+            None => sources.push((item.template_source()?, &item.start, &item.end, None)),
         }
-        .trim()
-        .to_lowercase();
 
-        let subtemplate_file = find_template(template, &subtemplate, base_dir, config_dir)
-            .with_context(|| {
-                format!(
-                    "Failed to locate subtemplate file {}/{}",
-                    template, subtemplate,
-                )
-            })?;
+        let mut built_templates = Vec::with_capacity(sources.len());
+        for (source, start, end, name) in sources {
+            //Template name and content
+            let (template_name, template_content) =
+                read_template(source, &scheme, base_dir, config_dir)?;
 
-        //Template content
-        let template_content = fs::read_to_string(&subtemplate_file)
-                       .with_context(||format!("Couldn't read template {}/{} at {:?}. Check if the correct template/subtemplate was specified, and run the update templates command if you didn't already.", template, subtemplate, subtemplate_file))?;
+            //Template with correct colors
+            let built_template = build_template(&template_content, &scheme, strict)
+                .context("Couldn't replace placeholders. Check if all colors on the specified scheme file are valid (don't include a leading '#').")?;
 
-        //Template with correct colors
-        let built_template = build_template(&template_content, &scheme)
-            .context("Couldn't replace placeholders. Check if all colors on the specified scheme file are valid (don't include a leading '#').")?;
+            built_templates.push((template_name, start, end, name, built_template));
+        }
 
         //File to write
         let file = shellexpand::full(&item.file)?.to_string();
 
+        //Write to symlink targets, instead of replacing symlinks
+        let follow_symlinks = item.follow_symlinks.unwrap_or(true);
+
         //Rewrite file with built template
         if rewrite {
-            std::path::Path::new(&file)
-                .parent()
-                .and_then(|p| fs::create_dir_all(p).ok());
-            fs::write(&file, built_template)
-                .with_context(|| format!("Couldn't write to file {:?}.", file))?;
+            let (template_name, _, _, _, built_template) = built_templates.remove(0);
+            write_file(&file, &built_template, follow_symlinks)?;
 
             if verbose {
-                println!("Wrote {}/{} on: {:?}", template, subtemplate, file)
+                println!("Wrote {} on: {:?}", template_name, file)
             }
         } else {
-            //Or replace with delimiters
-            let file_content = fs::read_to_string(&file)?;
-
-            match replace_delimiter(&file_content, &start, &end, &built_template) {
-                Ok(content) => fs::write(&file, content)
-                    .with_context(|| format!("Couldn't write to file {:?}", file))?,
-                Err(error) => eprintln!("Couldn't replace lines in {:?}: {}", file, error),
+            //Or replace with delimiters, all blocks at once
+            let mut file_content = read_target(&file, item.insert)?;
+            let mut replaced = false;
+
+            for (template_name, start, end, name, built_template) in built_templates {
+                let (start, end) = delimiters(&file, &file_content, start, end, name);
+
+                match (
+                    replace_delimiter(&file_content, &start, &end, &built_template),
+                    item.insert,
+                ) {
+                    (Ok(content), _) => {
+                        file_content = content;
+                        replaced = true;
+                        if verbose {
+                            println!("Wrote {} on {:?}", template_name, file);
+                        }
+                    }
+                    // Only insert the block if it's completely missing
+                    (Err(_), Some(insert)) if !contains_line(&file_content, &start) => {
+                        file_content =
+                            insert_block(&file_content, insert, &start, &end, &built_template);
+                        replaced = true;
+                        if verbose {
+                            println!("Inserted {} on {:?}", template_name, file);
+                        }
+                    }
+                    (Err(error), _) => eprintln!(
+                        "Couldn't replace lines in {:?} (between {:?} and {:?}): {}",
+                        file, start, end, error
+                    ),
+                }
             }
-            if verbose {
-                println!("Wrote {}/{} on {:?}", template, subtemplate, file);
+
+            if replaced {
+                write_file(&file, &file_content, follow_symlinks)?;
             }
         }
 
@@ -340,9 +676,19 @@ pub fn apply(
         }
     }
 
+    //Set the wallpaper the scheme was generated from
+    if let Some(image) = &image {
+        let command = config
+            .wallpaper
+            .as_ref()
+            .and_then(|wallpaper| wallpaper.hook.as_ref())
+            .map(|hook| hook.replace("{image}", &hook_argument(&image.to_string_lossy(), &shell)));
+        hooks.push(thread::spawn(move || run_hook(command, &shell, verbose)));
+    }
+
     let last_scheme_file = &base_dir.join("lastscheme");
 
-    fs::write(&last_scheme_file, &scheme.scheme_slug())
+    fs::write(last_scheme_file, scheme.scheme_slug())
         .with_context(|| "Couldn't update applied scheme name")?;
 
     while !hooks.is_empty() {
@@ -359,3 +705,248 @@ pub fn apply(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comment_style_by_file_type() {
+        assert_eq!(comment_style("~/.config/nvim/init.lua"), ("--", ""));
+        assert_eq!(comment_style("~/.vimrc"), ("\"", ""));
+        assert_eq!(comment_style("colors.vim"), ("\"", ""));
+        assert_eq!(comment_style("style.CSS"), ("/*", "*/"));
+        assert_eq!(comment_style("index.html"), ("<!--", "-->"));
+        assert_eq!(comment_style("~/.config/rofi/colors.rasi"), ("//", ""));
+        assert_eq!(
+            comment_style("~/.config/alacritty/alacritty.yml"),
+            ("#", "")
+        );
+        assert_eq!(comment_style("~/.Xresources"), ("#", ""));
+    }
+
+    #[test]
+    fn delimiters_follow_comment_style() {
+        assert_eq!(
+            delimiters("init.lua", "", &None, &None, None),
+            (
+                String::from("-- Start flavours"),
+                String::from("-- End flavours")
+            )
+        );
+        assert_eq!(
+            delimiters("style.css", "", &None, &None, Some("bar")),
+            (
+                String::from("/* Start flavours:bar */"),
+                String::from("/* End flavours:bar */")
+            )
+        );
+    }
+
+    #[test]
+    fn delimiters_configured() {
+        let (start, end) = (Some(String::from("-- start")), Some(String::from("-- end")));
+        assert_eq!(
+            delimiters("init.lua", "", &start, &end, None),
+            (String::from("-- start"), String::from("-- end"))
+        );
+    }
+
+    #[test]
+    fn delimiters_legacy_fallback() {
+        // Files that already use the '#' delimiters keep using them
+        let content = "x = 1\n# Start flavours\nold\n# End flavours\n";
+        assert_eq!(
+            delimiters("init.lua", content, &None, &None, None),
+            (
+                String::from("# Start flavours"),
+                String::from("# End flavours")
+            )
+        );
+        let content = "  # START FLAVOURS:bar  \n# End flavours:bar\n";
+        assert_eq!(
+            delimiters("style.css", content, &None, &None, Some("bar")).0,
+            "# Start flavours:bar"
+        );
+        // Unless they also have the new ones
+        let content = "# Start flavours\n-- Start flavours\n-- End flavours\n";
+        assert_eq!(
+            delimiters("init.lua", content, &None, &None, None).0,
+            "-- Start flavours"
+        );
+    }
+
+    #[test]
+    fn replace_every_pair() {
+        let content = "a\n# Start flavours\nold\n# End flavours\nb\n# start flavours\nold\nold\n  # END FLAVOURS\nc";
+        assert_eq!(
+            replace_delimiter(content, "# Start flavours", "# End flavours", "new").unwrap(),
+            "a\n# Start flavours\nnew\n# End flavours\nb\n# start flavours\nnew\n  # END FLAVOURS\nc\n"
+        );
+    }
+
+    #[test]
+    fn replace_missing_delimiters() {
+        let error = replace_delimiter("a\n", "# Start flavours", "# End flavours", "new");
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Couldn't find starting string."
+        );
+        // The second pair is never closed
+        let content = "# Start flavours\n# End flavours\n# Start flavours\nold\n";
+        let error = replace_delimiter(content, "# Start flavours", "# End flavours", "new");
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Couldn't find ending string."
+        );
+    }
+
+    #[test]
+    fn insert_into_file() {
+        let (start, end) = ("-- Start flavours", "-- End flavours");
+        assert_eq!(
+            insert_block("a = 1", Insert::Append, start, end, "new"),
+            "a = 1\n-- Start flavours\nnew\n-- End flavours\n"
+        );
+        assert_eq!(
+            insert_block("a = 1\n", Insert::Prepend, start, end, "new"),
+            "-- Start flavours\nnew\n-- End flavours\na = 1\n"
+        );
+    }
+
+    #[test]
+    fn insert_into_empty_file() {
+        for insert in [Insert::Append, Insert::Prepend] {
+            assert_eq!(
+                insert_block("", insert, "# Start flavours", "# End flavours", "new"),
+                "# Start flavours\nnew\n# End flavours\n"
+            );
+        }
+    }
+
+    #[test]
+    fn insert_into_missing_file() {
+        let file = std::env::temp_dir().join(format!("flavours-missing-{}", process::id()));
+        let file = file.to_string_lossy();
+        assert_eq!(read_target(&file, Some(Insert::Append)).unwrap(), "");
+        // Only if blocks are going to be inserted
+        assert!(read_target(&file, None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hook_argument_survives_the_wrapper() {
+        let dir = std::env::temp_dir().join(format!("flavours-hook-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("my wall's \"$HOME\".png");
+        let output = dir.join("output");
+
+        for shell in ["sh -c '{}'", "sh -c \"{}\""] {
+            let _ = fs::remove_file(&output);
+            let hook = format!("printf %s {} > {}", "{image}", output.display())
+                .replace("{image}", &hook_argument(&image.to_string_lossy(), shell));
+            run_hook(Some(hook), shell, false).unwrap();
+            assert_eq!(
+                fs::read_to_string(&output).unwrap(),
+                image.to_string_lossy(),
+                "with shell {}",
+                shell
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Creates an empty directory for a write test
+    #[cfg(unix)]
+    fn write_dir(name: &str) -> path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("flavours-write-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &path::Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[cfg(unix)]
+    fn set_mode(path: &path::Path, mode: u32) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_follows_symlinks() {
+        let dir = write_dir("follow");
+        let (target, link) = (dir.join("target"), dir.join("link"));
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_file(&link.to_string_lossy(), "new", true).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_replaces_symlinks() {
+        let dir = write_dir("replace");
+        let (target, link) = (dir.join("target"), dir.join("link"));
+        fs::write(&target, "old").unwrap();
+        set_mode(&target, 0o600);
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_file(&link.to_string_lossy(), "new", false).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_file());
+        assert_eq!(fs::read_to_string(&link).unwrap(), "new");
+        assert_eq!(mode(&link), 0o600);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        // The temporary file was moved over the link
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_dangling_symlinks() {
+        let dir = write_dir("dangling");
+        let (target, link) = (dir.join("target"), dir.join("link"));
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        // Following it would create its target
+        assert!(write_file(&link.to_string_lossy(), "new", true).is_err());
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert!(!target.exists());
+
+        // Replacing it doesn't need the target
+        write_file(&link.to_string_lossy(), "new", false).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_file());
+        assert_eq!(fs::read_to_string(&link).unwrap(), "new");
+        assert!(!target.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_keeps_mode() {
+        let dir = write_dir("mode");
+        let file = dir.join("file");
+        fs::write(&file, "old").unwrap();
+        set_mode(&file, 0o640);
+
+        write_file(&file.to_string_lossy(), "new", false).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(mode(&file), 0o640);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,20 @@
 use anyhow::Result;
 use base16_color_scheme::{Scheme, Template};
+
+use crate::operations::check::ensure_known_variables;
+use crate::scheme;
+
 /// Build a template
 ///
 /// Given template base and scheme, builds the template and returns it
 ///
 /// * `template_base` - Template base string
 /// * `scheme` - Scheme structure
-pub fn build_template(template_base: &str, scheme: &Scheme) -> Result<String> {
+/// * `strict` - Error out on variables that aren't part of the scheme
+pub fn build_template(template_base: &str, scheme: &Scheme, strict: bool) -> Result<String> {
+    if strict {
+        ensure_known_variables(template_base, scheme)?;
+    }
     let template = Template::new(template_base)?;
     Ok(template.render(scheme))
 }
@@ -15,13 +23,17 @@ pub fn build_template(template_base: &str, scheme: &Scheme) -> Result<String> {
 ///
 /// * `scheme_file` - Path to scheme file
 /// * `template_file` - Path to template
-pub fn build(scheme_slug: String, scheme_contents: &str, template_contents: &str) -> Result<()> {
-    let mut scheme: Scheme = serde_yaml::from_str(scheme_contents)?;
+/// * `strict` - Error out on variables that aren't part of the scheme
+pub fn build(
+    scheme_slug: String,
+    scheme_contents: &str,
+    template_contents: &str,
+    strict: bool,
+) -> Result<()> {
+    let mut scheme = scheme::parse(scheme_contents)?;
     scheme.slug = scheme_slug;
 
-    let template = Template::new(template_contents)?;
-
     //Template with correct colors
-    println!("{}", template.render(&scheme));
+    println!("{}", build_template(template_contents, &scheme, strict)?);
     Ok(())
 }
@@ -0,0 +1,358 @@
+use anyhow::{anyhow, Result};
+use base16_color_scheme::scheme::BaseIndex;
+use base16_color_scheme::template::TemplateField;
+use base16_color_scheme::Scheme;
+use std::fmt;
+
+/// Highest color a template may reference without a scheme to check against (base24 goes up to base17)
+const MAX_BASE_INDEX: u8 = 0x17;
+
+/// Kinds of problems a template can have
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// Variable that doesn't exist on the scheme, renders as an empty string
+    UnknownVariable(String),
+    /// Section that is opened but never closed
+    UnclosedSection(String),
+    /// Section that is closed without being opened
+    UnopenedSection(String),
+    /// Section closed with a different name than the one that was opened
+    MismatchedSection { expected: String, found: String },
+    /// Opening delimiter without a matching closing one
+    UnclosedTag,
+    /// Closing delimiter without a matching opening one
+    StrayDelimiter,
+    /// Tag kind our template engine doesn't support
+    Unsupported(&'static str),
+}
+
+/// Problem found on a template, and where
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            Problem::UnclosedSection(name) => write!(f, "section '{}' is never closed", name),
+            Problem::UnopenedSection(name) => {
+                write!(f, "section '{}' is closed but was never opened", name)
+            }
+            Problem::MismatchedSection { expected, found } => write!(
+                f,
+                "section '{}' is closed, but '{}' was the last one opened",
+                found, expected
+            ),
+            Problem::UnclosedTag => write!(f, "opening delimiter is never closed"),
+            Problem::StrayDelimiter => write!(f, "closing delimiter without an opening one"),
+            Problem::Unsupported(kind) => write!(f, "{} are not supported", kind),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.problem)
+    }
+}
+
+/// Get line and column (both starting at 1) of a byte offset
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Check if a variable name is part of the scheme variable set
+///
+/// * `name` - Variable name, as written on the template
+/// * `scheme` - Scheme to check colors against. If ommited, any base16 or base24 color is accepted
+fn is_known(name: &str, scheme: Option<&Scheme>) -> bool {
+    match TemplateField::parse_field(name) {
+        TemplateField::UnparsableField => false,
+        TemplateField::ColorField(field) => match scheme {
+            Some(scheme) => scheme.colors.contains_key(&BaseIndex(field.number)),
+            None => field.number <= MAX_BASE_INDEX,
+        },
+        _ => true,
+    }
+}
+
+/// Lint a template
+///
+/// Parses the template the same way our template engine does, collecting every problem found
+/// instead of stopping at the first one.
+///
+/// * `template` - Template contents
+/// * `scheme` - Scheme to check variables against, if any
+pub fn lint(template: &str, scheme: Option<&Scheme>) -> Vec<Diagnostic> {
+    let mut problems: Vec<(usize, Problem)> = Vec::new();
+    // Opened sections, with their name and offset
+    let mut sections: Vec<(&str, usize)> = Vec::new();
+
+    let mut offset = 0;
+    loop {
+        let rest = &template[offset..];
+        let (open, close) = (rest.find("{{"), rest.find("}}"));
+
+        let start = match (open, close) {
+            (None, None) => break,
+            // Closing delimiter comes first
+            (None, Some(close)) => {
+                problems.push((offset + close, Problem::StrayDelimiter));
+                offset += close + 2;
+                continue;
+            }
+            (Some(open), Some(close)) if close < open => {
+                problems.push((offset + close, Problem::StrayDelimiter));
+                offset += close + 2;
+                continue;
+            }
+            (Some(open), _) => offset + open,
+        };
+
+        let tag = &template[start..];
+        let (sigil, body_start, closing) = if tag.starts_with("{{{") {
+            ('&', 3, "}}}")
+        } else {
+            match tag[2..].chars().next() {
+                Some(sigil @ ('#' | '^' | '/' | '>' | '!' | '&' | '=')) => (sigil, 3, "}}"),
+                _ => (' ', 2, "}}"),
+            }
+        };
+
+        let body = &tag[body_start..];
+        let body_end = match body.find(closing) {
+            Some(end) => end,
+            None => {
+                problems.push((start, Problem::UnclosedTag));
+                break;
+            }
+        };
+        // Anything but comments can't contain another opening delimiter
+        if sigil != '!' {
+            if let Some(next_open) = body[..body_end].find("{{") {
+                problems.push((start, Problem::UnclosedTag));
+                offset = start + body_start + next_open;
+                continue;
+            }
+        }
+        let name = body[..body_end].trim();
+        offset = start + body_start + body_end + closing.len();
+
+        match sigil {
+            '!' => {}
+            '=' => problems.push((start, Problem::Unsupported("set delimiter tags"))),
+            '>' => problems.push((start, Problem::Unsupported("partials"))),
+            '#' | '^' => {
+                if !is_known(name, scheme) {
+                    problems.push((start, Problem::UnknownVariable(name.into())));
+                }
+                sections.push((name, start));
+            }
+            '/' => match sections.pop() {
+                None => problems.push((start, Problem::UnopenedSection(name.into()))),
+                Some((opened, _)) if opened != name => problems.push((
+                    start,
+                    Problem::MismatchedSection {
+                        expected: opened.into(),
+                        found: name.into(),
+                    },
+                )),
+                Some(_) => {}
+            },
+            _ => {
+                for variable in name.split_whitespace() {
+                    if !is_known(variable, scheme) {
+                        problems.push((start, Problem::UnknownVariable(variable.into())));
+                    }
+                }
+            }
+        }
+    }
+
+    for (name, start) in sections {
+        problems.push((start, Problem::UnclosedSection(name.into())));
+    }
+
+    problems.sort_by_key(|(start, _)| *start);
+    problems
+        .into_iter()
+        .map(|(start, problem)| {
+            let (line, column) = position(template, start);
+            Diagnostic {
+                line,
+                column,
+                problem,
+            }
+        })
+        .collect()
+}
+
+/// Make sure a template only uses variables the scheme has
+///
+/// * `template` - Template contents
+/// * `scheme` - Scheme the template will be built with
+pub fn ensure_known_variables(template: &str, scheme: &Scheme) -> Result<()> {
+    let unknown: Vec<String> = lint(template, Some(scheme))
+        .into_iter()
+        .filter(|diagnostic| matches!(diagnostic.problem, Problem::UnknownVariable(_)))
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Template uses variables that aren't part of the scheme:\n{}",
+            unknown.join("\n")
+        ))
+    }
+}
+
+/// Check template function
+///
+/// * `name` - Template name or path, used when reporting problems
+/// * `template` - Template contents
+/// * `verbose` - Should we be verbose?
+pub fn check_template(name: &str, template: &str, verbose: bool) -> Result<()> {
+    let diagnostics = lint(template, None);
+
+    for diagnostic in &diagnostics {
+        println!("{}:{}", name, diagnostic);
+    }
+
+    if diagnostics.is_empty() {
+        if verbose {
+            println!("No problems found on {}", name);
+        }
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Found {} problem(s) on template {}",
+            diagnostics.len(),
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lint a template without a scheme, keeping only the problems
+    fn problems(template: &str) -> Vec<Problem> {
+        lint(template, None)
+            .into_iter()
+            .map(|diagnostic| diagnostic.problem)
+            .collect()
+    }
+
+    #[test]
+    fn clean_template() {
+        let template = "{{scheme-name}}\n{{#base00-hex}}bg={{base00-hex}}{{/base00-hex}}\n{{! comment }}{{{base17-hex}}}";
+        assert_eq!(lint(template, None), vec![]);
+    }
+
+    #[test]
+    fn unknown_variable() {
+        assert_eq!(
+            problems("{{base00-hex}} {{base0G-hex}} {{nope}}"),
+            vec![
+                Problem::UnknownVariable("base0G-hex".into()),
+                Problem::UnknownVariable("nope".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_variable_on_scheme() {
+        let scheme: Scheme = serde_yaml::from_str(
+            "scheme: Test\nauthor: x\nbase00: \"000000\"\nbase01: \"111111\"\nbase02: \"222222\"\nbase03: \"333333\"\nbase04: \"444444\"\nbase05: \"555555\"\nbase06: \"666666\"\nbase07: \"777777\"\nbase08: \"888888\"\nbase09: \"999999\"\nbase0A: \"aaaaaa\"\nbase0B: \"bbbbbb\"\nbase0C: \"cccccc\"\nbase0D: \"dddddd\"\nbase0E: \"eeeeee\"\nbase0F: \"ffffff\"\n",
+        )
+        .unwrap();
+        // base24 colors are fine without a scheme, but not on a base16 one
+        assert!(lint("{{base17-hex}}", Some(&scheme))
+            .iter()
+            .any(|diagnostic| diagnostic.problem == Problem::UnknownVariable("base17-hex".into())));
+        assert!(ensure_known_variables("{{base0F-hex}}", &scheme).is_ok());
+        assert!(ensure_known_variables("{{base10-hex}}", &scheme).is_err());
+    }
+
+    #[test]
+    fn unclosed_section() {
+        assert_eq!(
+            problems("{{#base00-hex}}x"),
+            vec![Problem::UnclosedSection("base00-hex".into())]
+        );
+    }
+
+    #[test]
+    fn unopened_section() {
+        assert_eq!(
+            problems("x{{/base00-hex}}"),
+            vec![Problem::UnopenedSection("base00-hex".into())]
+        );
+    }
+
+    #[test]
+    fn mismatched_section() {
+        assert_eq!(
+            problems("{{#base00-hex}}x{{/base01-hex}}"),
+            vec![Problem::MismatchedSection {
+                expected: "base00-hex".into(),
+                found: "base01-hex".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn unclosed_tag() {
+        assert_eq!(problems("a {{base00-hex"), vec![Problem::UnclosedTag]);
+        // The next tag is still checked
+        assert_eq!(
+            problems("{{base00-hex {{nope}}"),
+            vec![
+                Problem::UnclosedTag,
+                Problem::UnknownVariable("nope".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn stray_delimiter() {
+        assert_eq!(
+            problems("a}} {{base00-hex}} b}}"),
+            vec![Problem::StrayDelimiter, Problem::StrayDelimiter]
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            problems("{{> partial}}{{=<% %>=}}"),
+            vec![
+                Problem::Unsupported("partials"),
+                Problem::Unsupported("set delimiter tags")
+            ]
+        );
+    }
+
+    #[test]
+    fn line_and_column() {
+        let diagnostics = lint("first line\n  ünï {{nope}}\n}}", None);
+        let positions: Vec<(usize, usize)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column))
+            .collect();
+        // Columns count characters, not bytes
+        assert_eq!(positions, vec![(2, 7), (3, 1)]);
+        assert_eq!(diagnostics[0].to_string(), "2:7: unknown variable 'nope'");
+    }
+}
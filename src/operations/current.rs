@@ -5,7 +5,7 @@ use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
 
 use crate::find::{find_schemes, get_luminance};
-use crate::scheme::Luminance;
+use crate::scheme::{self, Luminance};
 
 /// Get scheme by name
 ///
@@ -27,7 +27,7 @@ pub fn get_scheme(scheme_name: String, base_dir: &Path, config_dir: &Path) -> Re
     let scheme_contents = fs::read_to_string(scheme_file)
         .with_context(|| format!("Couldn't read scheme file at {:?}.", scheme_file))?;
 
-    let mut scheme: Scheme = serde_yaml::from_str(&scheme_contents)?;
+    let mut scheme = scheme::parse(&scheme_contents)?;
     scheme.slug = scheme_slug;
 
     Ok(scheme)
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Result};
+use palette::rgb::Rgb;
+use palette::{Clamp, FromColor, Lab};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+
+/// Names of the available palette extraction algorithms
+pub const ALGORITHMS: [&str; 4] = ["median-cut", "kmeans-lab", "octree", "histogram"];
+
+/// Palette extraction algorithm
+pub trait Extractor {
+    /// Extract a palette from an image, most dominant colors first
+    ///
+    /// * `pixels` - Image pixels, in RGBA
+    /// * `size` - Maximum amount of colors to extract
+    /// * `quality` - Sampling step (1 to 10). 1 looks at every pixel, higher values are faster but less accurate
+    fn extract(&self, pixels: &[u8], size: u8, quality: u8) -> Result<Vec<Rgb>>;
+}
+
+/// Get palette extraction algorithm by name
+///
+/// * `algorithm` - One of `ALGORITHMS`
+/// * `seed` - Seed for randomized algorithms, the same seed always gives the same palette
+pub fn extractor(algorithm: &str, seed: u64) -> Result<Box<dyn Extractor>> {
+    match algorithm {
+        "median-cut" => Ok(Box::new(MedianCut)),
+        "kmeans-lab" => Ok(Box::new(KMeansLab { seed })),
+        "octree" => Ok(Box::new(Octree)),
+        "histogram" => Ok(Box::new(Histogram)),
+        _ => Err(anyhow!(
+            "Unknown palette extraction algorithm '{}'. Use one of: {}",
+            algorithm,
+            ALGORITHMS.join(", ")
+        )),
+    }
+}
+
+/// Get every `quality`-th pixel, skipping transparent and almost white ones (as color_thief does)
+fn sample(pixels: &[u8], quality: u8) -> impl Iterator<Item = [u8; 3]> + '_ {
+    pixels
+        .chunks_exact(4)
+        .step_by(quality.max(1) as usize)
+        .filter(|pixel| pixel[3] >= 125 && !(pixel[0] > 250 && pixel[1] > 250 && pixel[2] > 250))
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+}
+
+fn to_rgb([r, g, b]: [u8; 3]) -> Rgb {
+    Rgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+fn to_lab(color: [u8; 3]) -> Lab {
+    Lab::from_color(to_rgb(color))
+}
+
+fn lab_distance(lab1: &Lab, lab2: &Lab) -> f32 {
+    (lab1.l - lab2.l).powi(2) + (lab1.a - lab2.a).powi(2) + (lab1.b - lab2.b).powi(2)
+}
+
+/// Modified median cut quantization, courtesy of color_thief
+pub struct MedianCut;
+
+impl Extractor for MedianCut {
+    fn extract(&self, pixels: &[u8], size: u8, quality: u8) -> Result<Vec<Rgb>> {
+        let palette =
+            color_thief::get_palette(pixels, color_thief::ColorFormat::Rgba, quality, size)?;
+
+        Ok(palette
+            .into_iter()
+            .map(|color| to_rgb([color.r, color.g, color.b]))
+            .collect())
+    }
+}
+
+/// K-means clustering on the CIELAB color space, which is perceptually uniform
+pub struct KMeansLab {
+    /// Seed for picking the initial centers
+    pub seed: u64,
+}
+
+/// Clustering is quadratic-ish, so we look at no more than this many pixels
+const KMEANS_MAX_SAMPLES: usize = 100_000;
+const KMEANS_MAX_ITERATIONS: usize = 20;
+
+impl Extractor for KMeansLab {
+    fn extract(&self, pixels: &[u8], size: u8, quality: u8) -> Result<Vec<Rgb>> {
+        let mut samples: Vec<Lab> = sample(pixels, quality).map(to_lab).collect();
+        if samples.len() > KMEANS_MAX_SAMPLES {
+            let step = (samples.len() + KMEANS_MAX_SAMPLES - 1) / KMEANS_MAX_SAMPLES;
+            samples = samples.into_iter().step_by(step).collect();
+        }
+        if samples.is_empty() {
+            return Err(anyhow!("Failed to find colors on image"));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        // Pick initial centers with k-means++: each new one is likelier to be far from the others
+        let mut centers = vec![samples[rng.gen_range(0..samples.len())]];
+        let mut distances: Vec<f32> = samples
+            .iter()
+            .map(|sample| lab_distance(sample, &centers[0]))
+            .collect();
+        while centers.len() < size as usize {
+            let total: f32 = distances.iter().sum();
+            if total <= 0.0 {
+                // Every sample is already a center
+                break;
+            }
+            let mut target = rng.gen_range(0.0..total);
+            let chosen = distances
+                .iter()
+                .position(|distance| {
+                    target -= distance;
+                    target <= 0.0
+                })
+                .unwrap_or(samples.len() - 1);
+            let center = samples[chosen];
+            for (distance, sample) in distances.iter_mut().zip(&samples) {
+                *distance = distance.min(lab_distance(sample, &center));
+            }
+            centers.push(center);
+        }
+
+        // Then move each center to the mean of its cluster, until they settle
+        let mut counts = vec![0usize; centers.len()];
+        for _ in 0..KMEANS_MAX_ITERATIONS {
+            let mut sums = vec![(0.0, 0.0, 0.0); centers.len()];
+            counts = vec![0; centers.len()];
+
+            for sample in &samples {
+                let (closest, _) = centers
+                    .iter()
+                    .map(|center| lab_distance(sample, center))
+                    .enumerate()
+                    .fold((0, f32::MAX), |closest, current| {
+                        if current.1 < closest.1 {
+                            current
+                        } else {
+                            closest
+                        }
+                    });
+                sums[closest].0 += sample.l;
+                sums[closest].1 += sample.a;
+                sums[closest].2 += sample.b;
+                counts[closest] += 1;
+            }
+
+            let mut moved = false;
+            for ((center, (l, a, b)), count) in centers.iter_mut().zip(sums).zip(&counts) {
+                if *count == 0 {
+                    continue;
+                }
+                let count = *count as f32;
+                let mean = Lab::new(l / count, a / count, b / count);
+                if lab_distance(center, &mean) > 0.01 {
+                    moved = true;
+                }
+                *center = mean;
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        let mut clusters: Vec<(usize, Lab)> = counts
+            .into_iter()
+            .zip(centers)
+            .filter(|(count, _)| *count > 0)
+            .collect();
+        clusters.sort_by_key(|(count, _)| Reverse(*count));
+
+        Ok(clusters
+            .into_iter()
+            .map(|(_, center)| Rgb::from_color(center).clamp())
+            .collect())
+    }
+}
+
+/// Octree quantization, merging the least used branches until the palette fits
+pub struct Octree;
+
+/// How many bits per channel the octree looks at
+const OCTREE_DEPTH: usize = 6;
+
+#[derive(Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    leaf: bool,
+    sum: [u64; 3],
+    count: u64,
+}
+
+impl Extractor for Octree {
+    fn extract(&self, pixels: &[u8], size: u8, quality: u8) -> Result<Vec<Rgb>> {
+        let mut nodes = vec![OctreeNode::default()];
+        // Nodes that can be merged into, by depth
+        let mut reducible: Vec<Vec<usize>> = vec![Vec::new(); OCTREE_DEPTH];
+        reducible[0].push(0);
+        let mut leaves = 0;
+
+        for color in sample(pixels, quality) {
+            let mut node = 0;
+            let mut depth = 0;
+            while !nodes[node].leaf {
+                let bit = 7 - depth;
+                let child = (((color[0] >> bit) & 1) << 2
+                    | ((color[1] >> bit) & 1) << 1
+                    | ((color[2] >> bit) & 1)) as usize;
+
+                node = match nodes[node].children[child] {
+                    Some(existing) => existing,
+                    None => {
+                        let created = nodes.len();
+                        let leaf = depth + 1 == OCTREE_DEPTH;
+                        nodes.push(OctreeNode {
+                            leaf,
+                            ..Default::default()
+                        });
+                        if leaf {
+                            leaves += 1;
+                        } else {
+                            reducible[depth + 1].push(created);
+                        }
+                        nodes[node].children[child] = Some(created);
+                        created
+                    }
+                };
+                depth += 1;
+            }
+
+            let leaf = &mut nodes[node];
+            for (sum, channel) in leaf.sum.iter_mut().zip(color) {
+                *sum += channel as u64;
+            }
+            leaf.count += 1;
+        }
+
+        // Merge the least used deepest branches into their parents
+        while leaves > size as usize {
+            let level = match reducible.iter_mut().rev().find(|level| !level.is_empty()) {
+                Some(level) => level,
+                None => break,
+            };
+            // Deepest reducible nodes only have leaves as children
+            let subtree_count = |node: &usize| -> u64 {
+                nodes[*node]
+                    .children
+                    .iter()
+                    .flatten()
+                    .map(|child| nodes[*child].count)
+                    .sum()
+            };
+            let (position, _) = level
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, node)| subtree_count(node))
+                .ok_or_else(|| anyhow!("Failed to reduce palette"))?;
+            let node = level.swap_remove(position);
+
+            let children: Vec<usize> = nodes[node].children.iter().flatten().copied().collect();
+            for child in &children {
+                let (sum, count) = (nodes[*child].sum, nodes[*child].count);
+                for (total, channel) in nodes[node].sum.iter_mut().zip(sum) {
+                    *total += channel;
+                }
+                nodes[node].count += count;
+            }
+            nodes[node].children = Default::default();
+            nodes[node].leaf = true;
+            leaves = leaves + 1 - children.len();
+        }
+
+        let mut colors: Vec<&OctreeNode> = Vec::with_capacity(leaves);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &nodes[node];
+            if node.leaf {
+                if node.count > 0 {
+                    colors.push(node);
+                }
+            } else {
+                stack.extend(node.children.iter().flatten());
+            }
+        }
+        if colors.is_empty() {
+            return Err(anyhow!("Failed to find colors on image"));
+        }
+        colors.sort_by_key(|node| Reverse(node.count));
+
+        Ok(colors
+            .into_iter()
+            .map(|node| {
+                let [r, g, b] = node.sum.map(|sum| (sum / node.count) as u8);
+                to_rgb([r, g, b])
+            })
+            .collect())
+    }
+}
+
+/// Most frequent colors on a coarse histogram, skipping ones too similar to those already picked
+pub struct Histogram;
+
+/// How many bits per channel the histogram looks at
+const HISTOGRAM_BITS: u8 = 4;
+
+/// Minimum (squared) CIELAB distance between picked colors
+const HISTOGRAM_MIN_DISTANCE: f32 = 100.0;
+
+impl Extractor for Histogram {
+    fn extract(&self, pixels: &[u8], size: u8, quality: u8) -> Result<Vec<Rgb>> {
+        let shift = 8 - HISTOGRAM_BITS;
+        let mut bins = vec![([0u64; 3], 0u64); 1 << (HISTOGRAM_BITS * 3)];
+
+        for color in sample(pixels, quality) {
+            let index = ((color[0] >> shift) as usize) << (HISTOGRAM_BITS * 2)
+                | ((color[1] >> shift) as usize) << HISTOGRAM_BITS
+                | (color[2] >> shift) as usize;
+            let (sum, count) = &mut bins[index];
+            for (sum, channel) in sum.iter_mut().zip(color) {
+                *sum += channel as u64;
+            }
+            *count += 1;
+        }
+
+        let mut bins: Vec<([u8; 3], u64)> = bins
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(sum, count)| (sum.map(|sum| (sum / count) as u8), count))
+            .collect();
+        if bins.is_empty() {
+            return Err(anyhow!("Failed to find colors on image"));
+        }
+        bins.sort_by_key(|(_, count)| Reverse(*count));
+
+        let mut picked: Vec<([u8; 3], Lab)> = Vec::with_capacity(size as usize);
+        for (color, _) in bins {
+            if picked.len() >= size as usize {
+                break;
+            }
+            let lab = to_lab(color);
+            if picked
+                .iter()
+                .all(|(_, other)| lab_distance(&lab, other) >= HISTOGRAM_MIN_DISTANCE)
+            {
+                picked.push((color, lab));
+            }
+        }
+
+        Ok(picked.into_iter().map(|(color, _)| to_rgb(color)).collect())
+    }
+}
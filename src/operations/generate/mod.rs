@@ -0,0 +1,1120 @@
+use anyhow::{anyhow, Context, Result};
+use base16_color_scheme::scheme::{BaseIndex, RgbColor};
+use base16_color_scheme::Scheme;
+use image::imageops::FilterType;
+use image::GenericImageView;
+use palette::convert::FromColorUnclamped;
+use palette::rgb::Rgb;
+use palette::{Clamp, FromColor, Hsl, IsWithinBounds, Lch, ShiftHue, Yxy};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::find::color_luminance;
+use crate::operations::info;
+
+pub mod extraction;
+
+use self::extraction::Extractor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Light,
+    Dark,
+}
+
+/// Pre-processing done to images before extracting their palette
+pub struct ImageOptions {
+    /// Region (x, y, width, height) to use instead of the whole image
+    pub crop: Option<(u32, u32, u32, u32)>,
+    /// Pixels less opaque than this (0 to 255) are ignored
+    pub alpha_threshold: u8,
+    /// Images larger than this (on either side) are downscaled to fit it
+    pub max_dimension: Option<u32>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            crop: None,
+            alpha_threshold: 125,
+            max_dimension: Some(1024),
+        }
+    }
+}
+
+/// Minimum WCAG contrast ratios (from 1 to 21) generated colors must have against the background
+pub struct MinContrast {
+    /// For the foreground (base05) and accents (base08 to base0F)
+    pub text: f32,
+    /// For comments (base03)
+    pub comments: f32,
+}
+
+impl Default for MinContrast {
+    fn default() -> Self {
+        // WCAG AA for normal text, and for large text
+        MinContrast {
+            text: 4.5,
+            comments: 3.0,
+        }
+    }
+}
+
+/// Where a generated scheme came from
+///
+/// Stored as comments on top of the scheme file, so it's still a valid scheme for everything else
+#[derive(Debug)]
+pub struct Provenance {
+    /// Image the scheme was generated from
+    pub image: PathBuf,
+    /// SHA-256 of the image contents, in hex
+    pub sha256: String,
+    /// Luminance mode asked for when generating (auto, dark or light)
+    pub mode: String,
+}
+
+const PROVENANCE_IMAGE: &str = "# flavours-source-image: ";
+const PROVENANCE_SHA256: &str = "# flavours-source-sha256: ";
+const PROVENANCE_MODE: &str = "# flavours-source-mode: ";
+
+/// How a scheme was generated, for debugging and tuning
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Palette the scheme was generated from, most dominant first
+    pub palette: Vec<String>,
+    /// Luminance mode used
+    pub mode: Mode,
+    /// Light color picked from the palette
+    pub light: Pick,
+    /// Dark color picked from the palette
+    pub dark: Pick,
+    /// Adjustments made to the colors, in order
+    pub adjustments: Vec<Adjustment>,
+    /// Final colors, from base00 to base0F
+    pub colors: BTreeMap<String, String>,
+}
+
+/// Color picked from the palette
+#[derive(Debug, Serialize)]
+pub struct Pick {
+    pub color: String,
+    /// On which pass (starting at 1) the color was found. The last pass falls back to the most dominant color
+    pub pass: u8,
+}
+
+/// Adjustment made to a color
+#[derive(Debug, Serialize)]
+pub struct Adjustment {
+    /// Which color was adjusted (such as background, foreground or base08)
+    pub color: String,
+    /// What was adjusted: luma, saturation or contrast
+    pub property: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+impl Adjustment {
+    fn new(color: &str, property: &'static str, from: Rgb, to: Rgb) -> Result<Adjustment> {
+        Ok(Adjustment {
+            color: color.into(),
+            property,
+            from: to_hex(from)?,
+            to: to_hex(to)?,
+        })
+    }
+}
+
+/// Color harmony rules, used to build a palette from seed colors
+pub enum Harmony {
+    /// Neighbouring hues
+    Analogous,
+    /// Hues evenly spaced around the color wheel
+    Triadic,
+    /// Opposite hues
+    Complementary,
+}
+
+/// Accent roles (base08 to base0F) and their hues in degrees, as per the styling guide
+/// https://github.com/tinted-theming/home/blob/main/styling.md
+///
+/// Brown gets its own hue, between red and orange, instead of being a darker or desaturated
+/// orange: images have their browns (wood, leather, soil) there, and a darker orange would take
+/// them from red as often as from orange. Brown and orange are still close, so `accent_colors`
+/// tells them apart by lightness, and brown is made duller after normalizing luma.
+const ACCENTS: [(&str, f32); 8] = [
+    ("red", 0.0),
+    ("orange", 30.0),
+    ("yellow", 60.0),
+    ("green", 120.0),
+    ("cyan", 180.0),
+    ("blue", 225.0),
+    ("magenta", 290.0),
+    ("brown", 25.0),
+];
+
+/// How far (in degrees) a color's hue can be from an accent role to be picked for it
+const MAX_HUE_DISTANCE: f32 = 30.0;
+
+/// Colors with less chroma (on CIE LCh) than this don't really have a hue, so they're not used as
+/// accents. HSL saturation isn't used, as it's high for pale colors such as creams.
+const MIN_ACCENT_CHROMA: f32 = 20.0;
+
+/// How much of its chroma brown (base0F) keeps, so it stays apart from red and orange once every
+/// accent gets the same luma
+const BROWN_CHROMA: f32 = 0.5;
+
+/// How much of the accents' luma brown (base0F) gets on light schemes, where it can be darker
+const BROWN_LIGHT_LUMA: f32 = 0.7;
+
+/// Colors with less chroma than this are less likely to be picked as accents, over more colorful
+/// ones with a similar hue
+const FULL_ACCENT_CHROMA: f32 = 40.0;
+
+/// Bring a color into the sRGB gamut
+///
+/// Reduces its chroma (on CIE LCh) instead of clipping each channel, which would shift its hue
+fn gamut_map(color: Rgb) -> Rgb {
+    if color.is_within_bounds() {
+        return color;
+    }
+
+    let lch: Lch = Lch::from_color_unclamped(color);
+    let in_gamut =
+        |chroma: f32| -> Rgb { Rgb::from_color_unclamped(Lch::new(lch.l, chroma, lch.hue)) };
+
+    // Find the highest chroma that fits, with a binary search
+    let (mut low, mut high) = (0.0, lch.chroma);
+    for _ in 0..16 {
+        let chroma = (low + high) / 2.0;
+        if in_gamut(chroma).is_within_bounds() {
+            low = chroma;
+        } else {
+            high = chroma;
+        }
+    }
+
+    // Lightness itself might be out of bounds
+    in_gamut(low).clamp()
+}
+
+fn to_hex(color: Rgb) -> Result<String> {
+    let (r, g, b) = gamut_map(color).into_components();
+    let to_u8 = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let color_u8 = [to_u8(r), to_u8(g), to_u8(b)];
+    let color_hex = hex::encode(color_u8);
+
+    Ok(color_hex)
+}
+
+fn grab_sat_luma(color: Rgb) -> (f32, f32) {
+    let yxy: Yxy = Yxy::from_color(color);
+    let (_, _, luma) = yxy.into_components();
+    let hsl: Hsl = Hsl::from_color(color);
+    let (_, saturation, _) = hsl.into_components();
+
+    (saturation, luma)
+}
+
+fn sum_colors(color1: Rgb, color2: Rgb, ratio: f32) -> Rgb {
+    let (r1, g1, b1) = color1.into_components();
+    let (r2, g2, b2) = color2.into_components();
+    let result: Rgb = Rgb::from_components((
+        (r2 * ratio + r1 * (1.0 - ratio)),
+        (g2 * ratio + g1 * (1.0 - ratio)),
+        (b2 * ratio + b1 * (1.0 - ratio)),
+    ));
+
+    result
+}
+
+fn hue_distance(hue1: f32, hue2: f32) -> f32 {
+    let distance = (hue1 - hue2).abs() % 360.0;
+    distance.min(360.0 - distance)
+}
+
+/// Pick accent colors (base08 to base0F), assigning image colors to the role with the closest hue
+///
+/// Roles without a close enough color (or with only neutral ones) get one synthesized, using the
+/// image's dominant chroma
+fn accent_colors(colors: &[Rgb], verbose: bool) -> [Rgb; 8] {
+    let candidates: Vec<(Hsl, f32)> = colors
+        .iter()
+        .map(|color| (Hsl::from_color(*color), Lch::from_color(*color).chroma))
+        .filter(|(_, chroma)| *chroma >= MIN_ACCENT_CHROMA)
+        .collect();
+
+    // Every role and candidate combination, with how far apart they are
+    let mut pairs = Vec::with_capacity(ACCENTS.len() * candidates.len());
+    for (role, (name, hue)) in ACCENTS.iter().enumerate() {
+        for (candidate, (hsl, chroma)) in candidates.iter().enumerate() {
+            let mut distance = hue_distance(hsl.hue.into_positive_degrees(), *hue);
+            // Prefer colorful colors
+            distance += (FULL_ACCENT_CHROMA - chroma).max(0.0) * 0.5;
+            // Brown and orange share hues, brown is told apart by being darker
+            match *name {
+                "brown" => distance += (hsl.lightness - 0.35).max(0.0) * 60.0,
+                "orange" => distance += (0.35 - hsl.lightness).max(0.0) * 60.0,
+                _ => {}
+            }
+            pairs.push((distance, role, candidate));
+        }
+    }
+    pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    // Assign closest pairs first, using each color only once
+    let mut accents: [Option<Rgb>; 8] = [None; 8];
+    let mut used = vec![false; candidates.len()];
+    for (distance, role, candidate) in pairs {
+        if distance <= MAX_HUE_DISTANCE && accents[role].is_none() && !used[candidate] {
+            accents[role] = Some(Rgb::from_color(candidates[candidate].0));
+            used[candidate] = true;
+        }
+    }
+
+    // Colors are sorted by dominance, so the first candidate has the dominant chroma
+    let (saturation, lightness) = candidates.first().map_or((0.5, 0.5), |(hsl, _)| {
+        (hsl.saturation.max(0.35), hsl.lightness.clamp(0.35, 0.65))
+    });
+
+    let mut result = [Rgb::from_components((0.0, 0.0, 0.0)); 8];
+    for (role, (name, hue)) in ACCENTS.iter().enumerate() {
+        result[role] = accents[role].unwrap_or_else(|| {
+            if verbose {
+                println!("No {} on image, synthesizing it", name);
+            }
+            let hsl: Hsl = match *name {
+                "brown" => Hsl::new(*hue, saturation * 0.7, lightness * 0.7),
+                _ => Hsl::new(*hue, saturation, lightness),
+            };
+            Rgb::from_color(hsl)
+        });
+    }
+
+    result
+}
+
+/// WCAG contrast ratio between two colors, from 1 to 21
+/// https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+fn contrast_ratio(color1: Rgb, color2: Rgb) -> f32 {
+    // Yxy's luma is the relative luminance WCAG uses
+    let (_, _, luma1) = Yxy::from_color(color1).into_components();
+    let (_, _, luma2) = Yxy::from_color(color2).into_components();
+
+    (luma1.max(luma2) + 0.05) / (luma1.min(luma2) + 0.05)
+}
+
+/// Change a color's lightness until it has enough contrast against the background
+///
+/// Lightness is changed on CIE LCh, so hue and chroma are kept as much as possible. The color is
+/// made lighter or darker, whichever allows more contrast against the background.
+fn ensure_contrast(color: Rgb, background: Rgb, min_contrast: f32) -> Rgb {
+    if contrast_ratio(color, background) >= min_contrast {
+        return color;
+    }
+
+    let (_, _, background_luma) = Yxy::from_color(background).into_components();
+    // Best contrasts we could get with white and black
+    let step = if 1.05 / (background_luma + 0.05) >= (background_luma + 0.05) / 0.05 {
+        1.0
+    } else {
+        -1.0
+    };
+
+    let mut lch: Lch = Lch::from_color(color);
+    let mut result = color;
+    while contrast_ratio(result, background) < min_contrast {
+        lch.l += step;
+        if !(0.0..=100.0).contains(&lch.l) {
+            break;
+        }
+        result = gamut_map(Rgb::from_color_unclamped(lch));
+    }
+
+    result
+}
+
+fn color_pass(
+    colors: &[Rgb],
+    min_luma: Option<f32>,
+    max_luma: Option<f32>,
+    min_saturation: Option<f32>,
+    max_saturation: Option<f32>,
+) -> Option<Rgb> {
+    let predicate = |rgb: &Rgb| {
+        let (saturation, luma) = grab_sat_luma(*rgb);
+
+        (max_luma.is_none() || luma <= max_luma.unwrap())
+            && (min_luma.is_none() || luma >= min_luma.unwrap())
+            && (max_saturation.is_none() || saturation <= max_saturation.unwrap())
+            && (min_saturation.is_none() || saturation >= min_saturation.unwrap())
+    };
+
+    colors.iter().copied().find(predicate)
+}
+
+/// Pick the light color, and on which pass (starting at 1) it was found
+fn light_color(colors: &[Rgb], verbose: bool) -> Result<(Rgb, u8)> {
+    let mut passes = 1;
+    // Try to find a nice light color with low saturation
+    let mut light = color_pass(colors, Some(0.6), None, None, Some(0.4));
+
+    // Try again, but now we will accept saturated colors, as long as they're very bright
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.7), None, None, Some(0.85));
+    }
+
+    // Try again, same as first, but a little more permissive
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.5), None, None, Some(0.5));
+    }
+
+    // Try again, but accept more saturated colors
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.6), None, None, Some(0.85));
+    }
+
+    // Try again, but now we will accept darker colors, as long as they're not saturated
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.32), None, None, Some(0.4));
+    }
+
+    // Try again, but now we will accept even more saturated colors
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.4), None, None, None);
+    }
+
+    // Try again, with darker colors
+    if light.is_none() {
+        passes += 1;
+        light = color_pass(colors, Some(0.3), None, None, None);
+    }
+
+    // Ok, we didn't find anything usable. So let's just grab the most dominant color (we'll lighten it later)
+    if light.is_none() {
+        passes += 1;
+        light = colors.first().copied();
+    }
+
+    if verbose {
+        println!("Passes: {}", passes);
+    }
+
+    let light = light.ok_or_else(|| anyhow!("Failed to find colors on image"))?;
+    Ok((light, passes))
+}
+
+/// Pick the dark color, and on which pass (starting at 1) it was found
+fn dark_color(colors: &[Rgb], verbose: bool) -> Result<(Rgb, u8)> {
+    let mut passes = 1;
+    // Try to find a nice darkish color with at least a bit of color
+    let mut dark = color_pass(colors, Some(0.012), Some(0.1), Some(0.18), Some(0.9));
+
+    // Try again, but now we will accept colors with any saturations, as long long as they're dark but not very dark
+    if dark.is_none() {
+        passes += 1;
+        dark = color_pass(colors, Some(0.012), Some(0.1), None, None);
+    }
+
+    // Try again, but now we will accept darker colors too
+    if dark.is_none() {
+        passes += 1;
+        dark = color_pass(colors, None, Some(0.1), None, None);
+    }
+
+    // Ok, we didn't find anything usable. So let's just grab the most dominant color (we'll darken it later)
+    if dark.is_none() {
+        passes += 1;
+        dark = colors.first().copied()
+    }
+
+    if verbose {
+        println!("Passes: {}", passes);
+    }
+
+    let dark = dark.ok_or_else(|| anyhow!("Failed to find colors on image"))?;
+    Ok((dark, passes))
+}
+
+/// Set a color's luma, keeping its chromaticity
+fn with_luma(color: Rgb, luma: f32) -> Rgb {
+    let yxy: Yxy = Yxy::from_color(color);
+    let (x, y, _) = yxy.into_components();
+    let yxy: Yxy = Yxy::from_components((x, y, luma));
+    gamut_map(Rgb::from_color_unclamped(yxy))
+}
+
+/// Set a color's saturation, keeping its hue and lightness
+fn with_saturation(color: Rgb, saturation: f32) -> Rgb {
+    let hsl: Hsl = Hsl::from_color(color);
+    let (h, _, l) = hsl.into_components();
+    let hsl: Hsl = Hsl::from_components((h, saturation, l));
+    Rgb::from_color(hsl)
+}
+
+fn fix_colors(
+    dark: Rgb,
+    light: Rgb,
+    mode: &Mode,
+    adjustments: &mut Vec<Adjustment>,
+) -> Result<(Rgb, Rgb)> {
+    let mut adjust = |color: &'static str, property: &'static str, from: Rgb, to: Rgb| {
+        adjustments.push(Adjustment::new(color, property, from, to)?);
+        Ok::<Rgb, anyhow::Error>(to)
+    };
+
+    match mode {
+        Mode::Light => {
+            let mut fg = dark;
+            let mut bg = light;
+            // Foreground should be pretty dark and have:
+            // luma <= 0.015 && saturation <= 0.65
+            let (saturation, luma) = grab_sat_luma(fg);
+            if luma > 0.015 {
+                fg = adjust("foreground", "luma", fg, with_luma(fg, 0.015))?;
+            }
+            if saturation > 0.65 {
+                fg = adjust("foreground", "saturation", fg, with_saturation(fg, 0.65))?;
+            }
+
+            // Background should be light have:
+            // luma >= 0.7 && saturation <= 0.12
+            let (saturation, luma) = grab_sat_luma(light);
+            if luma < 0.75 {
+                bg = adjust("background", "luma", bg, with_luma(bg, 0.75))?;
+            }
+            if saturation > 0.12 {
+                bg = adjust("background", "saturation", bg, with_saturation(bg, 0.15))?;
+            }
+            Ok((bg, fg))
+        }
+        Mode::Dark => {
+            let mut fg = light;
+            let mut bg = dark;
+            // Foreground should be light and have:
+            // luma >= 0.6 && saturation <= 0.15
+            let (saturation, luma) = grab_sat_luma(light);
+            if luma < 0.6 {
+                fg = adjust("foreground", "luma", fg, with_luma(fg, 0.6))?;
+            }
+            if saturation > 0.15 {
+                fg = adjust("foreground", "saturation", fg, with_saturation(fg, 0.15))?;
+            }
+            // Background should be dark and have:
+            // luma <= 0.02 && saturation <= 0.6
+            let (saturation, luma) = grab_sat_luma(dark);
+            if luma > 0.02 {
+                bg = adjust("background", "luma", bg, with_luma(bg, 0.02))?;
+            }
+            if saturation > 0.6 {
+                bg = adjust("background", "saturation", bg, with_saturation(bg, 0.6))?;
+            }
+            Ok((bg, fg))
+        }
+    }
+}
+
+/// Ensure a scheme color has enough contrast, telling about adjustments if verbose
+fn contrasted(
+    name: &str,
+    color: Rgb,
+    background: Rgb,
+    min_contrast: f32,
+    adjustments: &mut Vec<Adjustment>,
+    verbose: bool,
+) -> Result<Rgb> {
+    let result = ensure_contrast(color, background, min_contrast);
+    if result != color {
+        adjustments.push(Adjustment::new(name, "contrast", color, result)?);
+    }
+    if verbose && result != color {
+        println!(
+            "Adjusted {} for contrast: {} -> {} ({:.2}:1)",
+            name,
+            to_hex(color)?,
+            to_hex(result)?,
+            contrast_ratio(result, background)
+        );
+    }
+
+    Ok(result)
+}
+
+/// Parse a hex color, with or without a leading '#'
+///
+/// * `color` - Color string, such as `#7aa2f7`
+pub fn parse_color(color: &str) -> Result<Rgb> {
+    let mut rgb = [0u8; 3];
+    hex::decode_to_slice(color.trim().trim_start_matches('#'), &mut rgb)
+        .map_err(|_| anyhow!("Invalid color '{}'. Use hex colors such as #7aa2f7", color))?;
+    let [r, g, b] = rgb;
+
+    Ok(Rgb::new(
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0,
+    ))
+}
+
+/// Build a palette from seed colors, using a color harmony rule
+///
+/// Each seed gets companions with hues rotated according to the harmony. Darker and lighter
+/// versions of the first seed are added too, so there's something to base background and
+/// foreground on.
+///
+/// * `seeds` - Seed colors, most important first
+/// * `harmony` - Harmony rule to use
+pub fn harmony_palette(seeds: &[Rgb], harmony: &Harmony) -> Vec<Rgb> {
+    let rotations: &[f32] = match harmony {
+        Harmony::Analogous => &[-30.0, 30.0],
+        Harmony::Triadic => &[120.0, 240.0],
+        Harmony::Complementary => &[180.0],
+    };
+
+    let mut palette = seeds.to_vec();
+    for seed in seeds {
+        let hsl: Hsl = Hsl::from_color(*seed);
+        for rotation in rotations {
+            palette.push(Rgb::from_color(hsl.shift_hue(*rotation)));
+        }
+    }
+
+    if let Some(seed) = seeds.first() {
+        let hsl: Hsl = Hsl::from_color(*seed);
+        let hue = hsl.hue.into_positive_degrees();
+        palette.push(Rgb::from_color(Hsl::new(hue, hsl.saturation * 0.5, 0.1)));
+        palette.push(Rgb::from_color(Hsl::new(hue, hsl.saturation * 0.3, 0.9)));
+    }
+
+    palette
+}
+
+/// Parse a crop region
+///
+/// * `crop` - Region as `x,y,width,height`, in pixels
+pub fn parse_crop(crop: &str) -> Result<(u32, u32, u32, u32)> {
+    let invalid = || anyhow!("Invalid crop '{}'. Use x,y,width,height", crop);
+    let values = crop
+        .split(',')
+        .map(|value| value.trim().parse::<u32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u32>>>()?;
+
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok((x, y, width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Load an image's pixels (in RGBA), pre-processed according to the options
+///
+/// Pixels below the alpha threshold are made fully transparent, and the others fully opaque, so
+/// every extraction algorithm ignores the same ones.
+///
+/// * `image_path` - Image to load
+/// * `options` - Pre-processing options
+pub fn load_pixels(image_path: &Path, options: &ImageOptions) -> Result<Vec<u8>> {
+    let mut img_buffer =
+        image::open(image_path).with_context(|| format!("Couldn't open image {:?}", image_path))?;
+
+    if let Some((x, y, width, height)) = options.crop {
+        let (image_width, image_height) = img_buffer.dimensions();
+        if x.saturating_add(width) > image_width || y.saturating_add(height) > image_height {
+            return Err(anyhow!(
+                "Crop region {},{},{},{} is outside of image {:?} ({}x{})",
+                x,
+                y,
+                width,
+                height,
+                image_path,
+                image_width,
+                image_height
+            ));
+        }
+        img_buffer = img_buffer.crop_imm(x, y, width, height);
+    }
+
+    if let Some(max_dimension) = options.max_dimension {
+        let (width, height) = img_buffer.dimensions();
+        if width > max_dimension || height > max_dimension {
+            // Keeps the aspect ratio
+            img_buffer = img_buffer.resize(max_dimension, max_dimension, FilterType::Triangle);
+        }
+    }
+
+    let mut img_pixels = img_buffer.to_rgba8().into_raw();
+    for pixel in img_pixels.chunks_exact_mut(4) {
+        pixel[3] = if pixel[3] < options.alpha_threshold {
+            0
+        } else {
+            255
+        };
+    }
+
+    Ok(img_pixels)
+}
+
+/// Extract a palette from one or more images
+///
+/// Pixels from every image are put together, so a single palette blending all of them is
+/// extracted.
+///
+/// * `image_paths` - Images to extract colors from
+/// * `options` - Pre-processing done to each image
+/// * `extractor` - Palette extraction algorithm
+/// * `palette_size` - How many colors to extract from the images
+/// * `quality` - Sampling step for palette extraction (1 to 10)
+pub fn extract_palette(
+    image_paths: &[PathBuf],
+    options: &ImageOptions,
+    extractor: &dyn Extractor,
+    palette_size: u8,
+    quality: u8,
+) -> Result<Vec<Rgb>> {
+    let mut img_pixels = Vec::new();
+    for image_path in image_paths {
+        img_pixels.extend(load_pixels(image_path, options)?);
+    }
+
+    if !img_pixels.chunks_exact(4).any(|pixel| pixel[3] > 0) {
+        return Err(anyhow!("Images don't have any opaque enough pixels"));
+    }
+
+    // Use the chosen algorithm to get a palette
+    extractor.extract(img_pixels.as_slice(), palette_size, quality)
+}
+
+/// Decide whether a palette suits a light or dark scheme better
+///
+/// Uses the same luminance model as scheme filtering, so a generated scheme is listed under the
+/// luminance it was detected as.
+///
+/// * `palette` - Colors to look at
+/// * `verbose` - Should we be verbose?
+pub fn detect_mode(palette: &[Rgb], verbose: bool) -> Result<Mode> {
+    if palette.is_empty() {
+        return Err(anyhow!("Can't detect luminance mode without any colors"));
+    }
+
+    // Calculate the average luminance of the colors in the palette
+    let total_luminance: f32 = palette
+        .iter()
+        .map(|color| color_luminance(color.red, color.green, color.blue))
+        .sum();
+    let average_luminance = total_luminance / palette.len() as f32;
+
+    if verbose {
+        println!("The average luminance is: {}", average_luminance);
+    }
+
+    if average_luminance > 0.5 {
+        Ok(Mode::Light)
+    } else {
+        Ok(Mode::Dark)
+    }
+}
+
+/// Generate function
+///
+/// * `image_paths` - Images to generate the scheme from
+/// * `options` - Pre-processing done to each image
+/// * `mode` - Whether to generate a light or dark scheme. If ommited, it's detected from the images
+/// * `extractor` - Palette extraction algorithm
+/// * `palette_size` - How many colors to extract from the image
+/// * `quality` - Sampling step for palette extraction (1 to 10)
+/// * `min_contrast` - Minimum contrast against the background
+/// * `verbose` - Should we be verbose?
+#[allow(clippy::too_many_arguments)]
+pub fn generate(
+    image_paths: &[PathBuf],
+    options: &ImageOptions,
+    mode: Option<Mode>,
+    extractor: &dyn Extractor,
+    palette_size: u8,
+    quality: u8,
+    min_contrast: &MinContrast,
+    verbose: bool,
+) -> Result<VecDeque<String>> {
+    let palette = extract_palette(image_paths, options, extractor, palette_size, quality)?;
+    let mode = match mode {
+        Some(mode) => mode,
+        None => detect_mode(&palette, verbose)?,
+    };
+
+    generate_from_palette(&palette, mode, min_contrast, verbose)
+}
+
+/// Generate a scheme from a palette
+///
+/// The same palette always gets the same accent hues, so light and dark schemes generated from it
+/// match each other.
+///
+/// * `palette` - Colors to generate from, most dominant first
+/// * `mode` - Whether to generate a light or dark scheme
+/// * `min_contrast` - Minimum contrast against the background
+/// * `verbose` - Should we be verbose?
+pub fn generate_from_palette(
+    palette: &[Rgb],
+    mode: Mode,
+    min_contrast: &MinContrast,
+    verbose: bool,
+) -> Result<VecDeque<String>> {
+    let report = generate_report(palette, mode, min_contrast, verbose)?;
+
+    Ok(report.colors.into_values().collect())
+}
+
+/// Generate a scheme from a palette, reporting how it was done
+///
+/// * `palette` - Colors to generate from, most dominant first
+/// * `mode` - Whether to generate a light or dark scheme
+/// * `min_contrast` - Minimum contrast against the background
+/// * `verbose` - Should we be verbose?
+pub fn generate_report(
+    palette: &[Rgb],
+    mode: Mode,
+    min_contrast: &MinContrast,
+    verbose: bool,
+) -> Result<Report> {
+    let generated_colors = {
+        let mut colors: Vec<Rgb> = Vec::with_capacity(palette.len());
+        for &color in palette {
+            colors.push(color);
+            if verbose {
+                info::print_color(&to_hex(color)?)?;
+            }
+        }
+        // Remove duplicates
+        colors.dedup();
+
+        colors
+    };
+
+    // Get dominant light color
+    let (light, light_pass) = light_color(&generated_colors, verbose)?;
+    // Get dominant dark color
+    let (dark, dark_pass) = dark_color(&generated_colors, verbose)?;
+
+    // If verbose, print them
+    if verbose {
+        info::print_color(&to_hex(light)?)?;
+        info::print_color(&to_hex(dark)?)?;
+        println!()
+    }
+
+    // Make a few adjustments to the dominant colors
+    let mut adjustments = Vec::new();
+    let (background, foreground) = fix_colors(dark, light, &mode, &mut adjustments)?;
+    let foreground = contrasted(
+        "base05",
+        foreground,
+        background,
+        min_contrast.text,
+        &mut adjustments,
+        verbose,
+    )?;
+
+    // If light, white. If dark, black.
+    // We'll use this to make the color spectrum
+    let override_color = match &mode {
+        Mode::Light => Rgb::from_components((0.0, 0.0, 0.0)),
+        Mode::Dark => Rgb::from_components((1.0, 1.0, 1.0)),
+    };
+
+    // Add the main colors to the vector
+    let mut colors = VecDeque::new();
+
+    colors.push_back(to_hex(background)?);
+    colors.push_back(to_hex(sum_colors(background, foreground, 0.2))?);
+    colors.push_back(to_hex(sum_colors(background, foreground, 0.4))?);
+    colors.push_back(to_hex(contrasted(
+        "base03",
+        sum_colors(background, foreground, 0.6),
+        background,
+        min_contrast.comments,
+        &mut adjustments,
+        verbose,
+    )?)?);
+    colors.push_back(to_hex(sum_colors(background, foreground, 0.8))?);
+    colors.push_back(to_hex(foreground)?);
+    colors.push_back(to_hex(sum_colors(foreground, override_color, 0.15))?);
+    colors.push_back(to_hex(sum_colors(foreground, override_color, 0.3))?);
+
+    // Now for the trim colors
+    for (index, mut color) in accent_colors(&generated_colors, verbose)
+        .iter()
+        .copied()
+        .enumerate()
+    {
+        // Change luma to something a bit more constant
+        color = {
+            // Get convert to yxy and get components
+            let yxy: Yxy = Yxy::from_color(color);
+            let (x, y, luma) = yxy.into_components();
+
+            // Get our intended luma
+            let luma = match mode {
+                Mode::Light => luma.clamp(0.1, 0.12),
+                Mode::Dark => luma.max(0.19),
+            };
+            let brown = index == 7;
+            let luma = match mode {
+                Mode::Light if brown => luma * BROWN_LIGHT_LUMA,
+                _ => luma,
+            };
+
+            // Build yxy again and convert back to rgb
+            let yxy: Yxy = Yxy::from_components((x, y, luma));
+
+            if brown {
+                // Otherwise it's told apart from orange and red only by luma, which was just evened
+                let mut lch: Lch = Lch::from_color_unclamped(yxy);
+                lch.chroma *= BROWN_CHROMA;
+                gamut_map(Rgb::from_color_unclamped(lch))
+            } else {
+                gamut_map(Rgb::from_color_unclamped(yxy))
+            }
+        };
+        let color = contrasted(
+            &format!("base{:02X}", index + 8),
+            color,
+            background,
+            min_contrast.text,
+            &mut adjustments,
+            verbose,
+        )?;
+        // Add to the colors vector
+        colors.push_back(to_hex(color)?);
+    }
+
+    // If verbose, print our generated colors
+    if verbose {
+        println!();
+        for color in &colors {
+            info::print_color(color)?;
+        }
+    }
+
+    Ok(Report {
+        palette: generated_colors
+            .iter()
+            .map(|color| to_hex(*color))
+            .collect::<Result<_>>()?,
+        mode,
+        light: Pick {
+            color: to_hex(light)?,
+            pass: light_pass,
+        },
+        dark: Pick {
+            color: to_hex(dark)?,
+            pass: dark_pass,
+        },
+        adjustments,
+        colors: colors
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| (format!("base{:02X}", index), color))
+            .collect(),
+    })
+}
+
+impl Provenance {
+    /// Get the provenance of a scheme generated from an image, hashing it
+    ///
+    /// * `image_path` - Image the scheme is generated from
+    /// * `mode` - Luminance mode asked for (auto, dark or light)
+    pub fn from_image(image_path: &Path, mode: &str) -> Result<Provenance> {
+        let image = fs::read(image_path)
+            .with_context(|| format!("Couldn't read image {:?}", image_path))?;
+
+        Ok(Provenance {
+            image: image_path.into(),
+            sha256: hex::encode(Sha256::digest(&image)),
+            mode: mode.into(),
+        })
+    }
+
+    /// Comment lines to put on top of the scheme file
+    pub fn header(&self) -> String {
+        format!(
+            "{}{}\n{}{}\n{}{}\n",
+            PROVENANCE_IMAGE,
+            self.image.display(),
+            PROVENANCE_SHA256,
+            self.sha256,
+            PROVENANCE_MODE,
+            self.mode
+        )
+    }
+
+    /// Read the provenance from a scheme file's contents, if it has one
+    ///
+    /// * `scheme_contents` - Scheme file contents
+    pub fn read(scheme_contents: &str) -> Option<Provenance> {
+        let (mut image, mut sha256, mut mode) = (None, None, None);
+        for line in scheme_contents
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+        {
+            if let Some(value) = line.strip_prefix(PROVENANCE_IMAGE) {
+                image = Some(PathBuf::from(value));
+            } else if let Some(value) = line.strip_prefix(PROVENANCE_SHA256) {
+                sha256 = Some(value.to_string());
+            } else if let Some(value) = line.strip_prefix(PROVENANCE_MODE) {
+                mode = Some(value.to_string());
+            }
+        }
+
+        Some(Provenance {
+            image: image?,
+            sha256: sha256?,
+            mode: mode?,
+        })
+    }
+}
+
+/// Build a scheme from generated colors
+///
+/// * `colors` - base00 to base0F, in hex
+/// * `name` - Scheme display name
+/// * `slug` - Scheme slug
+/// * `author` - Scheme author
+pub fn build_scheme(
+    colors: VecDeque<String>,
+    name: String,
+    slug: String,
+    author: String,
+) -> Result<Scheme> {
+    Ok(Scheme {
+        scheme: name,
+        slug,
+        author,
+        colors: colors
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let mut rgb_color = [0u8; 3];
+                hex::decode_to_slice(color, &mut rgb_color)?;
+                Ok((BaseIndex(index.try_into()?), RgbColor(rgb_color)))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?,
+    })
+}
+
+/// Generate a scheme file from an image, using the default settings
+///
+/// If the scheme file was already generated from an image with the same contents (and mode), it's
+/// reused instead.
+///
+/// * `image_path` - Image to generate the scheme from
+/// * `mode` - Luminance mode (auto, dark or light)
+/// * `slug` - Scheme slug, also used as its name
+/// * `scheme_file` - Where to store the scheme
+/// * `verbose` - Should we be verbose?
+///
+/// Returns the scheme file contents
+pub fn generate_cached(
+    image_path: &Path,
+    mode: &str,
+    slug: &str,
+    scheme_file: &Path,
+    verbose: bool,
+) -> Result<String> {
+    let provenance = Provenance::from_image(image_path, mode)?;
+
+    if let Ok(contents) = fs::read_to_string(scheme_file) {
+        if let Some(cached) = Provenance::read(&contents) {
+            if cached.sha256 == provenance.sha256 && cached.mode == provenance.mode {
+                if verbose {
+                    println!("Image didn't change, reusing {:?}", scheme_file);
+                }
+                return Ok(contents);
+            }
+        }
+    }
+
+    let extractor = extraction::extractor("median-cut", 0)?;
+    let palette = extract_palette(
+        &[image_path.to_path_buf()],
+        &ImageOptions::default(),
+        extractor.as_ref(),
+        15,
+        1,
+    )?;
+    let mode = match mode {
+        "dark" => Mode::Dark,
+        "light" => Mode::Light,
+        _ => detect_mode(&palette, verbose)?,
+    };
+    let colors = generate_from_palette(&palette, mode, &MinContrast::default(), verbose)?;
+    let scheme = build_scheme(colors, slug.into(), slug.into(), "flavours".into())?;
+
+    let contents = format!("{}{}", provenance.header(), serde_yaml::to_string(&scheme)?);
+    if let Some(parent) = scheme_file.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Couldn't create directory {:?}", parent))?;
+    }
+    fs::write(scheme_file, &contents)
+        .with_context(|| format!("Couldn't write scheme file at {:?}", scheme_file))?;
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hsl(hue: f32, saturation: f32, lightness: f32) -> Rgb {
+        Rgb::from_color(Hsl::new(hue, saturation, lightness))
+    }
+
+    fn hex(color: Rgb) -> String {
+        to_hex(color).unwrap()
+    }
+
+    #[test]
+    fn accents_by_hue() {
+        let (red, green, blue) = (
+            hsl(0.0, 1.0, 0.5),
+            hsl(120.0, 1.0, 0.5),
+            hsl(240.0, 1.0, 0.5),
+        );
+        let accents = accent_colors(&[blue, red, green], false);
+        assert_eq!(hex(accents[0]), "ff0000");
+        assert_eq!(hex(accents[3]), "00ff00");
+        assert_eq!(hex(accents[5]), "0000ff");
+    }
+
+    #[test]
+    fn neutrals_arent_accents() {
+        // A cream, which has a high HSL saturation but almost no chroma
+        let cream = hsl(40.0, 0.8, 0.92);
+        let accents = accent_colors(&[cream], false);
+        assert!(accents.iter().all(|accent| hex(*accent) != hex(cream)));
+    }
+
+    #[test]
+    fn orange_and_brown_apart() {
+        let (orange, brown) = (hsl(30.0, 0.9, 0.55), hsl(25.0, 0.6, 0.25));
+        for colors in [[orange, brown], [brown, orange]] {
+            let accents = accent_colors(&colors, false);
+            assert_eq!(hex(accents[1]), hex(orange));
+            assert_eq!(hex(accents[7]), hex(brown));
+        }
+
+        // With a single candidate, one of them is synthesized
+        for color in [orange, brown] {
+            let accents = accent_colors(&[color], false);
+            assert_ne!(hex(accents[1]), hex(accents[7]));
+        }
+    }
+}
@@ -1,7 +1,32 @@
 pub mod apply;
 pub mod build;
+pub mod check;
 pub mod current;
 pub mod generate;
 pub mod info;
 pub mod list;
+pub mod status;
 pub mod update;
+
+///Prints rows lined up under a header
+pub(crate) fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    let header = header.map(String::from);
+    let widths: Vec<usize> = (0..N)
+        .map(|column| {
+            rows.iter()
+                .chain(Some(&header))
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for row in Some(&header).into_iter().chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
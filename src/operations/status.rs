@@ -0,0 +1,412 @@
+use anyhow::{Context, Result};
+use glob::{glob_with, MatchOptions};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, read_dir};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::lock::{LockedSource, Lockfile};
+use crate::operations::print_table;
+use crate::sources::{read_list, List};
+
+/// What's installed, and how it differs from the lists and configuration
+#[derive(Serialize, Debug)]
+pub struct Status {
+    pub schemes: Vec<SourceStatus>,
+    pub templates: Vec<SourceStatus>,
+    /// Extra sources on the configuration that were never updated
+    pub missing_extras: Vec<MissingExtra>,
+    /// Schemes on the configuration directory with the same slug as installed ones
+    pub overrides: Vec<Override>,
+}
+
+/// Structure for an installed (or listed) source
+#[derive(Serialize, Debug)]
+pub struct SourceStatus {
+    pub name: String,
+    pub state: State,
+    /// How many schemes or templates it has
+    pub files: usize,
+    /// When it was last updated, in seconds since the Unix epoch
+    pub updated: Option<u64>,
+    /// Revision on flavours.lock
+    pub rev: Option<String>,
+}
+
+/// How a source's directory matches its list
+#[derive(Debug, PartialEq, Eq)]
+pub enum State {
+    /// On the list and installed
+    Installed,
+    /// On the list, but not installed yet
+    Missing,
+    /// On the list, disabled, and not installed
+    Disabled,
+    /// Installed, but no longer on the list
+    Unlisted,
+    /// Schemes made by 'flavours generate'
+    Generated,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            State::Installed => write!(f, "installed"),
+            State::Missing => write!(f, "missing"),
+            State::Disabled => write!(f, "disabled"),
+            State::Unlisted => write!(f, "unlisted"),
+            State::Generated => write!(f, "generated"),
+        }
+    }
+}
+
+// Same as on the table
+impl Serialize for State {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Structure for an extra source that was never updated
+#[derive(Serialize, Debug)]
+pub struct MissingExtra {
+    pub kind: &'static str,
+    pub name: String,
+    pub source: String,
+}
+
+/// Structure for a scheme on the configuration directory shadowing installed ones
+#[derive(Serialize, Debug)]
+pub struct Override {
+    pub slug: String,
+    pub path: PathBuf,
+    pub shadows: Vec<PathBuf>,
+}
+
+///Finds files matching a glob pattern, skipping hidden ones
+fn find_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let options = MatchOptions {
+        require_literal_leading_dot: true,
+        ..MatchOptions::new()
+    };
+    let mut found = Vec::new();
+    for file in glob_with(pattern, options)? {
+        let file = file?;
+        // '**' can match the same file more than once
+        if file.is_file() && !found.contains(&file) {
+            found.push(file);
+        }
+    }
+    Ok(found)
+}
+
+///Counts a source's schemes or templates
+fn count_files(kind: &str, path: &Path) -> Result<usize> {
+    let pattern = match kind {
+        "schemes" => path.join("**").join("*.y*ml"),
+        _ => path.join("templates").join("*.mustache"),
+    };
+    Ok(find_files(&pattern.to_string_lossy())?.len())
+}
+
+///Gets when a path was last changed, in seconds since the Unix epoch
+fn modified(path: &Path) -> Option<u64> {
+    // Linked local sources were updated when the link was made
+    let modified = path
+        .symlink_metadata()
+        .and_then(|metadata| metadata.modified());
+    modified
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+}
+
+///Gets the status of every listed or installed source of a kind
+///
+///# Arguments
+///* `kind` - What the sources have (schemes or templates)
+///* `base16_dir` - Base16 directory
+///* `locked` - Locked sources of the same kind
+fn sources(
+    kind: &str,
+    base16_dir: &Path,
+    locked: &BTreeMap<String, LockedSource>,
+) -> Result<Vec<SourceStatus>> {
+    let list_path = base16_dir.join("sources").join(kind).join("list.yaml");
+    let list = match list_path.exists() {
+        true => read_list(&list_path)?,
+        false => List::new(),
+    };
+
+    let target = base16_dir.join(kind);
+    let mut installed = Vec::new();
+    if let Ok(entries) = read_dir(&target) {
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if !name.starts_with('.') {
+                installed.push(name);
+            }
+        }
+    }
+
+    let mut names: Vec<&String> = list.keys().chain(&installed).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let path = target.join(name);
+            let is_installed = installed.contains(name);
+            let state = match list.get(name) {
+                _ if kind == "schemes" && name == "generated" => State::Generated,
+                Some(_) if is_installed => State::Installed,
+                Some(source) if !source.enabled => State::Disabled,
+                Some(_) => State::Missing,
+                None => State::Unlisted,
+            };
+            let files = match is_installed {
+                true => count_files(kind, &path)?,
+                false => 0,
+            };
+            Ok(SourceStatus {
+                name: name.clone(),
+                state,
+                files,
+                updated: modified(&path).filter(|_| is_installed),
+                rev: locked.get(name).map(|locked| locked.rev.clone()),
+            })
+        })
+        .collect()
+}
+
+///Finds schemes on the configuration directory with the same slug as installed ones
+///
+///# Arguments
+///* `base16_dir` - Base16 directory
+///* `config_dir` - flavours' config dir
+fn overrides(base16_dir: &Path, config_dir: &Path) -> Result<Vec<Override>> {
+    let slug = |path: &Path| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    };
+    let pattern = |dir: PathBuf| dir.join("**").join("*.y*ml").to_string_lossy().into_owned();
+
+    let mut installed: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for path in find_files(&pattern(base16_dir.join("schemes")))? {
+        if let Some(slug) = slug(&path) {
+            installed.entry(slug).or_default().push(path);
+        }
+    }
+
+    let mut overrides = Vec::new();
+    for path in find_files(&pattern(config_dir.join("schemes")))? {
+        let slug = match slug(&path) {
+            Some(slug) => slug,
+            None => continue,
+        };
+        if let Some(shadows) = installed.get(&slug) {
+            overrides.push(Override {
+                shadows: shadows.clone(),
+                slug,
+                path,
+            });
+        }
+    }
+    overrides.sort_by(|a, b| a.slug.cmp(&b.slug));
+    Ok(overrides)
+}
+
+///Gets what's installed on the data directory, and how it differs from the lists and configuration
+///
+///# Arguments
+///* `base_dir` - flavours' base data dir
+///* `config_dir` - flavours' config dir
+///* `config_path` - Flavours configuration path, the lockfile lives next to it
+pub fn status(base_dir: &Path, config_dir: &Path, config_path: &Path) -> Result<Status> {
+    let base16_dir = base_dir.join("base16");
+    let lock = Lockfile::read(&Lockfile::path(config_path))?;
+
+    let config = match config_path.exists() {
+        true => {
+            let contents = fs::read_to_string(config_path)
+                .with_context(|| format!("Couldn't read configuration file {:?}.", config_path))?;
+            Some(Config::read(&contents, config_path)?)
+        }
+        false => None,
+    };
+
+    let mut missing_extras = Vec::new();
+    if let Some(config) = config {
+        let extras = [
+            ("schemes", config.extra_scheme),
+            ("templates", config.extra_template),
+        ];
+        for (kind, extra_sources) in extras {
+            for extra in extra_sources.unwrap_or_default() {
+                if !base16_dir.join(kind).join(&extra.name).exists() {
+                    missing_extras.push(MissingExtra {
+                        kind,
+                        name: extra.name,
+                        source: extra.source,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(Status {
+        schemes: sources("schemes", &base16_dir, &lock.schemes)?,
+        templates: sources("templates", &base16_dir, &lock.templates)?,
+        missing_extras,
+        overrides: overrides(&base16_dir, config_dir)?,
+    })
+}
+
+///Tells how long ago a time (in seconds since the Unix epoch) was
+fn ago(time: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(time);
+    let units = [("day", 24 * 60 * 60), ("hour", 60 * 60), ("minute", 60)];
+    let elapsed = now.saturating_sub(time);
+    match units.iter().find(|(_, seconds)| elapsed >= *seconds) {
+        Some((unit, seconds)) => match elapsed / seconds {
+            1 => format!("1 {} ago", unit),
+            count => format!("{} {}s ago", count, unit),
+        },
+        None => String::from("just now"),
+    }
+}
+
+impl Status {
+    ///Prints the status as tables
+    pub fn print(&self) {
+        for (kind, sources) in [("schemes", &self.schemes), ("templates", &self.templates)] {
+            let files: usize = sources.iter().map(|source| source.files).sum();
+            println!(
+                "{} ({} sources, {} {})",
+                kind.to_uppercase(),
+                sources.len(),
+                files,
+                kind
+            );
+            let rows: Vec<[String; 5]> = sources
+                .iter()
+                .map(|source| {
+                    [
+                        source.name.clone(),
+                        source.state.to_string(),
+                        source.files.to_string(),
+                        source.updated.map(ago).unwrap_or_default(),
+                        source
+                            .rev
+                            .as_deref()
+                            .map(|rev| rev.chars().take(12).collect())
+                            .unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            print_table(["NAME", "STATE", "FILES", "UPDATED", "REV"], &rows);
+            println!();
+        }
+
+        if !self.missing_extras.is_empty() {
+            println!("Extra sources never updated (run 'flavours update all'):");
+            for extra in &self.missing_extras {
+                println!("  {} {} ({})", extra.kind, extra.name, extra.source);
+            }
+            println!();
+        }
+
+        if !self.overrides.is_empty() {
+            println!("Schemes on the configuration directory shadowing installed ones:");
+            for scheme in &self.overrides {
+                println!("  {}: {:?}", scheme.slug, scheme.path);
+                for shadowed in &scheme.shadows {
+                    println!("    shadows {:?}", shadowed);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::{write_list, Source};
+
+    #[test]
+    fn status_of_sources() {
+        let dir = std::env::temp_dir().join(format!("flavours-status-{}", std::process::id()));
+        let (base_dir, config_dir) = (dir.join("data"), dir.join("config"));
+        let base16_dir = base_dir.join("base16");
+        let lists = base16_dir.join("sources").join("schemes");
+        fs::create_dir_all(&lists).unwrap();
+        let mut list = List::new();
+        for name in ["installed", "missing"] {
+            list.insert(name.to_string(), Source::from("https://example.com"));
+        }
+        write_list(&list, &lists.join("list.yaml")).unwrap();
+        for (source, scheme) in [("installed", "dark/one.yaml"), ("unlisted", "two.yml")] {
+            let path = base16_dir.join("schemes").join(source).join(scheme);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        // Hidden files aren't counted
+        fs::write(base16_dir.join("schemes/installed/.hidden.yaml"), "").unwrap();
+        let shadowing = config_dir.join("schemes").join("mine").join("one.yaml");
+        fs::create_dir_all(shadowing.parent().unwrap()).unwrap();
+        fs::write(&shadowing, "").unwrap();
+
+        let status = status(&base_dir, &config_dir, &dir.join("config.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+        let status = status.unwrap();
+
+        let schemes: Vec<(&str, &State, usize)> = status
+            .schemes
+            .iter()
+            .map(|source| (source.name.as_str(), &source.state, source.files))
+            .collect();
+        assert_eq!(
+            schemes,
+            [
+                ("installed", &State::Installed, 1),
+                ("missing", &State::Missing, 0),
+                ("unlisted", &State::Unlisted, 1),
+            ]
+        );
+        assert!(status.schemes[0].updated.is_some());
+        assert!(status.schemes[1].updated.is_none());
+        assert!(status.templates.is_empty());
+
+        assert_eq!(status.overrides.len(), 1);
+        assert_eq!(status.overrides[0].slug, "one");
+        assert_eq!(status.overrides[0].path, shadowing);
+        assert_eq!(
+            status.overrides[0].shadows,
+            [base16_dir.join("schemes/installed/dark/one.yaml")]
+        );
+    }
+
+    #[test]
+    fn state_names() {
+        for state in [
+            State::Installed,
+            State::Missing,
+            State::Disabled,
+            State::Unlisted,
+            State::Generated,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&state).unwrap(),
+                format!("\"{}\"", state)
+            );
+        }
+        assert_eq!(State::Unlisted.to_string(), "unlisted");
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs::create_dir_all;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::spawn;
+
+use anyhow::{anyhow, Context, Result};
+
+use super::progress::Reporter;
+use super::CloneType;
+use crate::sources::Source;
+
+///Files to checkout from the repository
+fn pathspec(clone_type: &CloneType) -> &'static str {
+    match clone_type {
+        CloneType::Scheme => "*.y*ml",
+        CloneType::Template => "templates",
+        CloneType::List => "list.yaml",
+    }
+}
+
+///Git command that never prompts for credentials
+fn git() -> Command {
+    let mut command = Command::new("git");
+    command.env("GIT_TERMINAL_PROMPT", "0");
+    command
+}
+
+///Runs a git command, erroring out if it fails
+///
+///Git's output is always captured, so output from sources updating at the same time doesn't get
+///mixed up. Its progress (or, when verbose, each line it prints) goes to `reporter`, and errors
+///are used on the error message.
+///
+///# Arguments
+///* `command` - Git command to run
+///* `action` - What the command does, used on error messages
+///* `verbose` - Show every line git prints, instead of only its progress
+///* `reporter` - Where to show git's output
+fn run_git(command: &mut Command, action: &str, verbose: bool, reporter: &Reporter) -> Result<()> {
+    let mut child = command
+        .stdout(match verbose {
+            true => Stdio::piped(),
+            false => Stdio::null(),
+        })
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Couldn't run git (is it installed?)")?;
+
+    // Read both at once, so git doesn't block writing to one of them
+    let stdout = child.stdout.take().map(|stdout| {
+        let reporter = reporter.clone();
+        spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                reporter.output(&line);
+            }
+        })
+    });
+
+    // Git redraws its progress with carriage returns
+    let mut error = None;
+    let mut last = None;
+    let mut line = Vec::new();
+    let stderr = child.stderr.take().map(BufReader::new);
+    let bytes = stderr.into_iter().flat_map(Read::bytes);
+    for byte in bytes.chain(Some(Ok(b'\n'))) {
+        let byte = byte.context("Couldn't read git's output")?;
+        if byte != b'\r' && byte != b'\n' {
+            line.push(byte);
+            continue;
+        }
+        let text = String::from_utf8_lossy(&line).trim().to_string();
+        line.clear();
+        if text.is_empty() {
+            continue;
+        }
+        if error.is_none() && (text.starts_with("fatal:") || text.starts_with("error:")) {
+            error = Some(text.clone());
+        }
+        match verbose {
+            true => reporter.output(&text),
+            false => reporter.progress(&text),
+        }
+        last = Some(text);
+    }
+    if let Some(stdout) = stdout {
+        let _ = stdout.join();
+    }
+
+    let status = child
+        .wait()
+        .context("Couldn't run git (is it installed?)")?;
+    if status.success() {
+        return Ok(());
+    }
+    match error.or(last) {
+        Some(reason) => Err(anyhow!("Git failed to {}: {}", action, reason)),
+        None => Err(anyhow!("Git failed to {}", action)),
+    }
+}
+
+///Uses git to update a repository, and then only checkout the .yml files, templates folder or list
+///
+///Repositories are kept (bare) on a cache, so only new commits are fetched.
+///
+///# Arguments
+///* `cache` - Path to the repository's bare cache
+///* `source` - Repository to fetch, with the branch and subdirectory to use
+///* `rev` - Commit to checkout, defaults to the latest one on the source's branch
+///* `verbose` - Boolean, tell git to be quiet if false
+///* `clone_type` - Which files to checkout
+///* `staging` - Where to checkout the files
+///* `reporter` - Where to show each phase and git's progress
+///
+///Returns the commit that was checked out
+pub fn clone(
+    cache: &Path,
+    source: &Source,
+    rev: Option<String>,
+    verbose: bool,
+    clone_type: &CloneType,
+    staging: &Path,
+    reporter: &Reporter,
+) -> Result<String> {
+    // Create the cache, if it doesn't exist yet
+    if !cache.join("HEAD").exists() {
+        create_dir_all(cache).with_context(|| format!("Couldn't create directory {:?}", cache))?;
+        run_git(
+            git().arg("init").arg("--quiet").arg("--bare").arg(cache),
+            "create repository cache",
+            verbose,
+            reporter,
+        )?;
+    }
+
+    // Fetch only what's new, or nothing if the pinned commit is already there
+    let cached = match &rev {
+        Some(rev) => git()
+            .arg("-C")
+            .arg(cache)
+            .arg("cat-file")
+            .arg("-e")
+            .arg(format!("{}^{{commit}}", rev))
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false),
+        None => false,
+    };
+    if !cached {
+        reporter.phase("fetching");
+        let mut fetch = git();
+        fetch.arg("-C").arg(cache).arg("fetch");
+        if !verbose {
+            fetch.arg("--quiet").arg("--progress");
+        }
+        fetch.arg("--depth").arg("1").arg(&source.source).arg(
+            rev.as_deref()
+                .or(source.reference.as_deref())
+                .unwrap_or("HEAD"),
+        );
+        run_git(&mut fetch, "fetch", verbose, reporter)?;
+    }
+
+    // Resolve which commit we got
+    let target = rev.as_deref().unwrap_or("FETCH_HEAD");
+    let output = git()
+        .arg("-C")
+        .arg(cache)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg(format!("{}^{{commit}}", target))
+        .output()
+        .context("Couldn't run git (is it installed?)")?;
+    if !output.status.success() {
+        return Err(anyhow!("Git failed to find commit {}", target));
+    }
+    let commit = String::from_utf8(output.stdout)?.trim().to_string();
+
+    // Checkout the files, only from the subdirectories asked for
+    let subdirectory = source.subdirectory()?.unwrap_or_else(|| Path::new(""));
+    let pathspecs: Vec<PathBuf> = match source.subdirectories()?.as_slice() {
+        [] => vec![subdirectory.join(pathspec(clone_type))],
+        subdirectories => subdirectories
+            .iter()
+            .map(|only| subdirectory.join(only).join(pathspec(clone_type)))
+            .collect(),
+    };
+    create_dir_all(staging).with_context(|| format!("Couldn't create directory {:?}", staging))?;
+    reporter.phase("checking out");
+    let mut checkout = git();
+    checkout
+        .arg("--git-dir")
+        .arg(cache)
+        .arg("--work-tree")
+        .arg(staging)
+        .arg("checkout")
+        .arg("--quiet");
+    if !verbose {
+        checkout.arg("--progress");
+    }
+    checkout.arg(&commit).arg("--").args(pathspecs);
+    run_git(&mut checkout, "checkout files", verbose, reporter)?;
+
+    Ok(commit)
+}
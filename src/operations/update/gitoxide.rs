@@ -0,0 +1,164 @@
+use std::fs::{create_dir_all, write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{sleep, spawn, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use gix::progress::tree::Root;
+use gix::remote::{fetch::Shallow, Direction};
+
+use super::progress::Reporter;
+use super::CloneType;
+use crate::sources::Source;
+
+/// Reference the fetched commit is kept at, on the cache
+const FETCHED: &str = "refs/flavours/fetched";
+
+/// How often gitoxide's progress is shown
+const POLL: Duration = Duration::from_millis(100);
+
+///Shows gitoxide's progress through a reporter, on another thread, until `done` is set
+///
+///Only the last task that made some progress is shown, such as `read pack: 450/1000`.
+fn watch(root: Arc<Root>, reporter: Reporter, done: Arc<AtomicBool>) -> JoinHandle<()> {
+    spawn(move || {
+        let mut tasks = Vec::new();
+        while !done.load(Ordering::SeqCst) {
+            root.sorted_snapshot(&mut tasks);
+            let current = tasks.iter().rev().find_map(|(_, task)| {
+                let value = task.progress.as_ref()?;
+                match (value.step.load(Ordering::SeqCst), &value.unit) {
+                    (0, _) => None,
+                    (step, Some(unit)) => Some(format!(
+                        "{}: {}",
+                        task.name,
+                        unit.display(step, value.done_at, None)
+                    )),
+                    (step, None) => match value.done_at {
+                        Some(done_at) => Some(format!("{}: {}/{}", task.name, step, done_at)),
+                        None => Some(format!("{}: {}", task.name, step)),
+                    },
+                }
+            });
+            if let Some(current) = current {
+                reporter.progress(&current);
+            }
+            sleep(POLL);
+        }
+    })
+}
+
+///Uses gitoxide to update a repository, and then only checkout the .yml files, templates folder
+///or list
+///
+///Repositories are kept (bare) on a cache, so only new commits are fetched. Only the files that
+///are needed are written, straight from the commit's tree.
+///
+///# Arguments
+///* `cache` - Path to the repository's bare cache
+///* `source` - Repository to fetch, with the branch and subdirectory to use
+///* `rev` - Commit to checkout, defaults to the latest one on the source's branch
+///* `verbose` - Boolean, tell what's being fetched if true
+///* `clone_type` - Which files to checkout
+///* `staging` - Where to checkout the files
+///* `reporter` - Where to show each phase and how it's going
+///
+///Returns the commit that was checked out
+pub fn clone(
+    cache: &Path,
+    source: &Source,
+    rev: Option<String>,
+    verbose: bool,
+    clone_type: &CloneType,
+    staging: &Path,
+    reporter: &Reporter,
+) -> Result<String> {
+    // Create the cache, if it doesn't exist yet
+    let repo = if cache.join("HEAD").exists() {
+        gix::open(cache)?
+    } else {
+        create_dir_all(cache).with_context(|| format!("Couldn't create directory {:?}", cache))?;
+        gix::init_bare(cache)?
+    };
+
+    // Fetch only what's new, or nothing if the pinned commit is already there
+    let pinned = rev
+        .as_deref()
+        .map(|rev| gix::ObjectId::from_hex(rev.as_bytes()))
+        .transpose()
+        .with_context(|| format!("Invalid commit {:?}", rev))?;
+    let commit = match pinned {
+        Some(pinned) if repo.find_commit(pinned).is_ok() => pinned,
+        _ => {
+            let wanted = rev
+                .as_deref()
+                .or(source.reference.as_deref())
+                .unwrap_or("HEAD");
+            if verbose {
+                reporter.output(&format!("fetching {} from {}", wanted, source.source));
+            }
+            reporter.phase("fetching");
+            let refspec = format!("+{}:{}", wanted, FETCHED);
+            let root = Root::new();
+            let done = Arc::new(AtomicBool::new(false));
+            let watcher = watch(root.clone(), reporter.clone(), done.clone());
+            let fetched = (|| -> Result<()> {
+                repo.remote_at(source.source.as_str())?
+                    .with_refspecs(Some(refspec.as_str()), Direction::Fetch)?
+                    .connect(Direction::Fetch)?
+                    .prepare_fetch(root.add_child("negotiate"), Default::default())?
+                    .with_shallow(Shallow::DepthAtRemote(
+                        NonZeroU32::new(1).expect("1 isn't zero"),
+                    ))
+                    .receive(root.add_child("fetch"), &AtomicBool::new(false))?;
+                Ok(())
+            })();
+            done.store(true, Ordering::SeqCst);
+            let _ = watcher.join();
+            fetched?;
+            repo.find_reference(FETCHED)?.peel_to_id()?.detach()
+        }
+    };
+
+    // Checkout only the files we need
+    reporter.phase("checking out");
+    let subdirectory = source.subdirectory()?.unwrap_or_else(|| Path::new(""));
+    let only = source.subdirectories()?;
+    let tree = repo.find_object(commit)?.peel_to_tree()?;
+    let mut recorder = gix::traverse::tree::Recorder::default();
+    tree.traverse().breadthfirst(&mut recorder)?;
+
+    let mut written = 0;
+    for entry in recorder.records.iter().filter(|entry| entry.mode.is_blob()) {
+        let path = PathBuf::from(entry.filepath.to_string());
+        let wanted = match path.strip_prefix(subdirectory) {
+            Ok(relative) => clone_type.wanted(relative, &only),
+            Err(_) => false,
+        };
+        if !wanted {
+            continue;
+        }
+
+        let target = staging.join(&path);
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)
+                .with_context(|| format!("Couldn't create directory {:?}", parent))?;
+        }
+        write(&target, &repo.find_object(entry.oid)?.data)
+            .with_context(|| format!("Couldn't write {:?}", target))?;
+        written += 1;
+        reporter.progress(&format!("{} files", written));
+    }
+    if written == 0 {
+        return Err(anyhow!(
+            "No {} found on {}",
+            clone_type.files(),
+            source.source
+        ));
+    }
+
+    Ok(commit.to_string())
+}
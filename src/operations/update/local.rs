@@ -0,0 +1,268 @@
+use std::fs::{self, create_dir_all, remove_dir_all, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+
+use super::progress::Reporter;
+use super::CloneType;
+use crate::sources::Source;
+
+/// Kinds of local sources
+enum Kind {
+    Directory,
+    TarGz,
+    Zip,
+}
+
+///Gets which kind of local source a path is
+fn kind(path: &Path) -> Result<Kind> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if path.is_dir() {
+        Ok(Kind::Directory)
+    } else if !path.exists() {
+        Err(anyhow!("Local source {:?} doesn't exist", path))
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(Kind::TarGz)
+    } else if name.ends_with(".zip") {
+        Ok(Kind::Zip)
+    } else {
+        Err(anyhow!(
+            "Local source {:?} isn't a directory, .tar.gz or .zip archive",
+            path
+        ))
+    }
+}
+
+///Copies the files a source needs, keeping their relative paths
+///
+///Symlinked directories are skipped (they could loop), and so are symlinked files pointing
+///outside of the source.
+///
+///# Arguments
+///* `from` - Directory to copy from
+///* `to` - Directory to copy into
+///* `relative` - Current directory, relative to both
+///* `clone_type` - Which files to copy
+///* `only` - Only copy files inside these directories, all of them if empty
+///* `copied` - How many files were copied so far
+///* `reporter` - Where to show how many files were copied
+fn copy_files(
+    from: &Path,
+    to: &Path,
+    relative: &Path,
+    clone_type: &CloneType,
+    only: &[&Path],
+    copied: &mut usize,
+    reporter: &Reporter,
+) -> Result<()> {
+    let dir = from.join(relative);
+    for entry in fs::read_dir(&dir).with_context(|| format!("Couldn't read {:?}", dir))? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let relative = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() && !links_inside(&entry.path(), from)? {
+            continue;
+        }
+        if file_type.is_dir() {
+            copy_files(from, to, &relative, clone_type, only, copied, reporter)?;
+        } else if clone_type.wanted(&relative, only) {
+            let target = to.join(&relative);
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent)
+                    .with_context(|| format!("Couldn't create directory {:?}", parent))?;
+            }
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("Couldn't copy {:?}", entry.path()))?;
+            *copied += 1;
+            reporter.progress(&format!("{} files", copied));
+        }
+    }
+    Ok(())
+}
+
+///Checks if a symlink points to a file inside a directory
+fn links_inside(link: &Path, dir: &Path) -> Result<bool> {
+    let (target, dir) = match (link.canonicalize(), dir.canonicalize()) {
+        (Ok(target), Ok(dir)) => (target, dir),
+        // Dangling
+        (Err(_), _) => return Ok(false),
+        (_, Err(error)) => return Err(error).with_context(|| format!("Couldn't find {:?}", dir)),
+    };
+    Ok(target.is_file() && target.starts_with(dir))
+}
+
+///Gets the directory an archive was extracted to, going into it if it has a single directory
+///(such as `base16-alacritty-master/`)
+fn archive_root(extracted: &Path) -> Result<PathBuf> {
+    let entries = fs::read_dir(extracted)
+        .with_context(|| format!("Couldn't read {:?}", extracted))?
+        .collect::<Result<Vec<_>, _>>()?;
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => Ok(entry.path()),
+        _ => Ok(extracted.to_path_buf()),
+    }
+}
+
+///Extracts an archive
+fn extract(archive: &Path, kind: &Kind, to: &Path) -> Result<()> {
+    let file = File::open(archive).with_context(|| format!("Couldn't open {:?}", archive))?;
+    match kind {
+        Kind::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(to)?,
+        Kind::Zip => zip::ZipArchive::new(file)?.extract(to)?,
+        Kind::Directory => unreachable!(),
+    }
+    Ok(())
+}
+
+///Hashes an archive, so it can be locked
+fn archive_hash(archive: &Path) -> Result<String> {
+    let contents = fs::read(archive).with_context(|| format!("Couldn't read {:?}", archive))?;
+    Ok(format!("sha256:{}", hex::encode(Sha256::digest(contents))))
+}
+
+///Gets a local source (directory or archive) into the staging path
+///
+///Directories are copied, or symlinked if the source says so. Archives are extracted, and then
+///copied. Only the files the source needs (schemes, templates or list) are copied.
+///
+///# Arguments
+///* `local` - Path of the local source
+///* `source` - Source, with the subdirectory to use
+///* `staging` - Where to put the files
+///* `rev` - Locked revision, archives must still have this hash
+///* `clone_type` - Which files to get
+///* `reporter` - Where to show each phase and how it's going
+///
+///Returns the revision: the hash of archives, or `directory`
+pub fn fetch(
+    local: &Path,
+    source: &Source,
+    staging: &Path,
+    rev: Option<&str>,
+    clone_type: &CloneType,
+    reporter: &Reporter,
+) -> Result<String> {
+    if source.link && source.subdirectories.is_some() {
+        return Err(anyhow!(
+            "'{}' can't be linked and have subdirectories, as the whole directory is linked",
+            source.source
+        ));
+    }
+    let kind = kind(local)?;
+    let subdirectory = source.subdirectory()?.unwrap_or_else(|| Path::new(""));
+
+    let (root, rev, extracted) = match kind {
+        Kind::Directory => (local.to_path_buf(), String::from("directory"), None),
+        Kind::TarGz | Kind::Zip => {
+            let hash = archive_hash(local)?;
+            if let Some(rev) = rev.filter(|rev| *rev != hash) {
+                return Err(anyhow!(
+                    "Archive {:?} changed since it was locked (was {}, is {})",
+                    local,
+                    rev,
+                    hash
+                ));
+            }
+            reporter.phase("extracting");
+            let extracted = staging.with_extension("extract");
+            let _ = remove_dir_all(&extracted);
+            create_dir_all(&extracted)
+                .with_context(|| format!("Couldn't create directory {:?}", extracted))?;
+            let result = extract(local, &kind, &extracted)
+                .with_context(|| format!("Couldn't extract {:?}", local))
+                .and_then(|_| archive_root(&extracted));
+            match result {
+                Ok(root) => (root, hash, Some(extracted)),
+                Err(error) => {
+                    let _ = remove_dir_all(&extracted);
+                    return Err(error);
+                }
+            }
+        }
+    };
+    let root = root.join(subdirectory);
+
+    let result = if source.link {
+        reporter.phase("linking");
+        link(&root, staging, &kind)
+    } else {
+        reporter.phase("copying");
+        let mut copied = 0;
+        create_dir_all(staging)
+            .with_context(|| format!("Couldn't create directory {:?}", staging))
+            .and_then(|_| source.subdirectories())
+            .and_then(|only| {
+                copy_files(
+                    &root,
+                    staging,
+                    Path::new(""),
+                    clone_type,
+                    &only,
+                    &mut copied,
+                    reporter,
+                )
+            })
+            .and_then(|_| match copied {
+                0 => Err(anyhow!("No {} found on {:?}", clone_type.files(), root)),
+                _ => Ok(()),
+            })
+    };
+    if let Some(extracted) = extracted {
+        let _ = remove_dir_all(extracted);
+    }
+
+    result.map(|_| rev)
+}
+
+///Symlinks a local directory into the staging path
+fn link(root: &Path, staging: &Path, kind: &Kind) -> Result<()> {
+    if !matches!(kind, Kind::Directory) {
+        return Err(anyhow!("Only directories can be linked, not archives"));
+    }
+    let root = root
+        .canonicalize()
+        .with_context(|| format!("Couldn't find {:?}", root))?;
+
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(&root, staging);
+    #[cfg(windows)]
+    let linked = std::os::windows::fs::symlink_dir(&root, staging);
+
+    linked.with_context(|| format!("Couldn't link {:?} to {:?}", staging, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::update::progress::Progress;
+    use std::sync::Arc;
+
+    #[test]
+    fn link_with_subdirectories() {
+        let dir = std::env::temp_dir().join(format!("flavours-link-{}", std::process::id()));
+        create_dir_all(dir.join("dark")).unwrap();
+        let mut source = Source::from(dir.to_string_lossy().as_ref());
+        source.link = true;
+        source.subdirectories = Some(vec![String::from("dark")]);
+        let reporter = Reporter::new(Arc::new(Progress::new(true)), "schemes", "linked");
+
+        let staging = dir.with_extension("checkout");
+        let result = fetch(&dir, &source, &staging, None, &CloneType::Scheme, &reporter);
+        let linked = staging.symlink_metadata().is_ok();
+        remove_dir_all(&dir).unwrap();
+
+        assert!(result.unwrap_err().to_string().ends_with(
+            "can't be linked and have subdirectories, as the whole directory is linked"
+        ));
+        assert!(!linked);
+    }
+}
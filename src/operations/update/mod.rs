@@ -0,0 +1,1122 @@
+use std::collections::BTreeMap;
+use std::fs::{
+    create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename, write,
+};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::lock::{LockedSource, Lockfile};
+use crate::operations::print_table;
+use crate::sources::{
+    read_list, write_list, List, Source, Sources, DEFAULT_SCHEMES, DEFAULT_TEMPLATES,
+};
+use anyhow::{anyhow, Context, Result};
+use glob::Pattern;
+
+#[cfg(not(feature = "gitoxide"))]
+mod git;
+#[cfg(feature = "gitoxide")]
+mod gitoxide;
+#[cfg(feature = "gitoxide")]
+use gitoxide as git;
+mod local;
+mod pool;
+mod progress;
+
+use progress::{Event, Progress, Reporter};
+
+///Gets schemes and templates list sources from the configuration, or from the sources file,
+///falling back to the default repos. The sources file is created if it doesn't exist yet.
+///
+///# Arguments
+///* `file` - Path to sources file
+///* `config` - Flavours configuration, its sources take precedence
+fn get_sources(file: &Path, config: &Config) -> Result<(Source, Source)> {
+    let sources = match Sources::read(file)? {
+        Some(sources) => sources,
+        None => {
+            let sources = Sources {
+                schemes: Some(Source::from(
+                    config.schemes.as_deref().unwrap_or(DEFAULT_SCHEMES),
+                )),
+                templates: Some(Source::from(
+                    config.templates.as_deref().unwrap_or(DEFAULT_TEMPLATES),
+                )),
+            };
+            sources.write(file)?;
+            sources
+        }
+    };
+
+    // Only use sources.yaml sources if sources not specified in config
+    let pick = |configured: &Option<String>, listed: Option<Source>, default: &str| match configured
+    {
+        Some(configured) => Source::from(configured.as_str()),
+        None => listed.unwrap_or_else(|| Source::from(default)),
+    };
+    Ok((
+        pick(&config.schemes, sources.schemes, DEFAULT_SCHEMES),
+        pick(&config.templates, sources.templates, DEFAULT_TEMPLATES),
+    ))
+}
+
+/// Which revision to update each source to
+pub enum Pinning<'a> {
+    /// Latest revision of every source
+    Latest,
+    /// Revisions on the lockfile
+    Locked,
+    /// Latest revision of the named source (optionally prefixed by its kind, as in
+    /// `schemes/gruvbox`), and the lockfile's for everything else
+    Bump(&'a str),
+}
+
+/// Sources and the revisions they were locked to
+type Locked = BTreeMap<String, LockedSource>;
+
+impl Pinning<'_> {
+    ///Gets the revision a source should be updated to, none meaning the latest one
+    ///
+    ///# Arguments
+    ///* `kind` - What the source has (lists, schemes or templates)
+    ///* `locked` - Locked sources of the same kind
+    ///* `name` - Source name
+    ///* `source` - Where the source is fetched from
+    fn rev(&self, kind: &str, locked: &Locked, name: &str, source: &str) -> Result<Option<String>> {
+        // Revisions locked for another source don't count
+        let pinned = locked
+            .get(name)
+            .filter(|locked| locked.source == source)
+            .map(|locked| locked.rev.clone());
+
+        match self {
+            Pinning::Latest => Ok(None),
+            Pinning::Bump(bumped) if bumps(bumped, kind, name) => Ok(None),
+            Pinning::Bump(_) => Ok(pinned),
+            Pinning::Locked => pinned.map(Some).ok_or_else(|| {
+                anyhow!(
+                    "'{}' ({}) isn't on the lockfile. Run update without --locked to add it.",
+                    name,
+                    source
+                )
+            }),
+        }
+    }
+}
+
+///Checks if a bumped name (optionally prefixed by its kind) is this source
+fn bumps(bumped: &str, kind: &str, name: &str) -> bool {
+    match bumped.split_once('/') {
+        Some((bumped_kind, bumped)) => bumped_kind == kind && bumped == name,
+        None => bumped == name,
+    }
+}
+
+///Makes sure a bumped source exists, on a single list, before updating anything
+///
+///# Arguments
+///* `bumped` - Name to bump, optionally prefixed by its kind
+///* `dir` - Base16 directory
+///* `lock` - Lockfile
+fn check_bump(bumped: &str, dir: &Path, lock: &Lockfile) -> Result<()> {
+    let mut kinds = Vec::new();
+    for (kind, locked) in [
+        ("lists", &lock.lists),
+        ("schemes", &lock.schemes),
+        ("templates", &lock.templates),
+    ] {
+        let mut names: Vec<String> = locked.keys().cloned().collect();
+        let list_path = dir.join("sources").join(kind).join("list.yaml");
+        match kind {
+            "lists" => names.extend([String::from("schemes"), String::from("templates")]),
+            _ if list_path.exists() => names.extend(read_list(&list_path)?.into_keys()),
+            _ => {}
+        }
+        if names.iter().any(|name| bumps(bumped, kind, name)) {
+            kinds.push(kind);
+        }
+    }
+
+    match kinds.as_slice() {
+        [] => Err(anyhow!("There's no source named '{}' to bump", bumped)),
+        [_] => Ok(()),
+        kinds => Err(anyhow!(
+            "There's a source named '{}' on {}. Use {} to pick one",
+            bumped,
+            kinds.join(" and "),
+            kinds
+                .iter()
+                .map(|kind| format!("'{}/{}'", kind, bumped))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum CloneType {
+    Scheme,
+    Template,
+    List,
+}
+
+impl CloneType {
+    /// Whether a file (relative to the source) should be copied from local sources
+    fn includes(&self, file: &Path) -> bool {
+        match self {
+            CloneType::Scheme => matches!(
+                file.extension().and_then(|extension| extension.to_str()),
+                Some("yaml") | Some("yml")
+            ),
+            CloneType::Template => file.starts_with("templates"),
+            CloneType::List => file == Path::new("list.yaml"),
+        }
+    }
+
+    /// Whether a file (relative to the source's subdirectory) should be checked out
+    ///
+    ///* `only` - Only files inside these directories, all of them if empty
+    fn wanted(&self, file: &Path, only: &[&Path]) -> bool {
+        self.includes(file) && (only.is_empty() || only.iter().any(|only| file.starts_with(only)))
+    }
+
+    /// What the files are, for error messages
+    fn files(&self) -> &'static str {
+        match self {
+            CloneType::Scheme => "schemes",
+            CloneType::Template => "templates",
+            CloneType::List => "list.yaml",
+        }
+    }
+}
+
+///Gets the cache directory for a repository
+///
+///# Arguments
+///* `dir` - Base16 directory
+///* `kind` - What the repository has (lists, schemes or templates)
+///* `name` - Repository name
+fn cache_dir(dir: &Path, kind: &str, name: &str) -> PathBuf {
+    dir.join("cache").join(kind).join(format!("{}.git", name))
+}
+
+///Updates a source, either a git repository or a local directory or archive
+///
+///Files are put on a temporary directory, which only replaces the current one if everything went
+///well.
+///
+///# Arguments
+///* `path` - File path where source files should be
+///* `cache` - Path to the repository's bare cache
+///* `source` - Source to get, with the branch and subdirectory to use
+///* `rev` - Revision to get, defaults to the latest one
+///* `verbose` - Boolean, tell git to be quiet if false
+///* `clone_type` - Which files to get
+///* `reporter` - Where to show each phase and how it's going
+///
+///Returns the revision that was fetched
+fn fetch(
+    path: &Path,
+    cache: &Path,
+    source: Source,
+    rev: Option<String>,
+    verbose: bool,
+    clone_type: CloneType,
+    reporter: &Reporter,
+) -> Result<String> {
+    if source.subdirectories.is_some() && !matches!(clone_type, CloneType::Scheme) {
+        return Err(anyhow!(
+            "'{}' has subdirectories, but only scheme sources can have them",
+            source.source
+        ));
+    }
+
+    let staging = cache.with_extension("checkout");
+    let _ = remove_dir_all(&staging);
+    if let Some(parent) = staging.parent() {
+        create_dir_all(parent)
+            .with_context(|| format!("Couldn't create directory {:?}", parent))?;
+    }
+    if verbose {
+        reporter.output(&format!("checking out on {:?}", path));
+    }
+
+    let local = source.local_path();
+    let fetched = match &local {
+        Some(local) => local::fetch(
+            local,
+            &source,
+            &staging,
+            rev.as_deref(),
+            &clone_type,
+            reporter,
+        ),
+        None => git::clone(
+            cache,
+            &source,
+            rev,
+            verbose,
+            &clone_type,
+            &staging,
+            reporter,
+        ),
+    };
+    let rev = match fetched {
+        Ok(rev) => rev,
+        Err(error) => {
+            let _ = remove_dir_all(&staging);
+            return Err(error);
+        }
+    };
+
+    // Swap the new files in
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)
+            .with_context(|| format!("Couldn't create directory {:?}", parent))?;
+    }
+    let old = cache.with_extension("old");
+    let _ = remove_dir_all(&old);
+    if path.symlink_metadata().is_ok() {
+        rename(path, &old).with_context(|| format!("Couldn't move {:?} out of the way", path))?;
+    }
+    // Git checks out the subdirectory itself, local sources only get its contents
+    let checked_out = match (&local, source.subdirectory()?) {
+        (None, Some(subdirectory)) => staging.join(subdirectory),
+        _ => staging.clone(),
+    };
+    if let Err(error) = rename(&checked_out, path) {
+        // Put the old files back
+        let _ = rename(&old, path);
+        let _ = remove_dir_all(&staging);
+        return Err(error).with_context(|| format!("Couldn't move new files into {:?}", path));
+    }
+    let _ = remove_dir_all(&staging);
+    let _ = remove_dir_all(&old);
+
+    Ok(rev)
+}
+
+/// Options for updating sources
+pub struct Options {
+    /// How many sources to update at once
+    pub jobs: usize,
+    /// How many times to retry sources that fail to download
+    pub retries: u32,
+    /// Stop on the first source that fails, and error out
+    pub fail_fast: bool,
+    /// Remove schemes and templates that are no longer on their lists
+    pub prune: bool,
+}
+
+/// How updating a source went
+enum Status {
+    /// Updated to this revision
+    Updated(String),
+    Failed(anyhow::Error),
+    /// Not updated, for this reason
+    Skipped(&'static str),
+    /// Removed, as it's no longer on the list
+    Pruned,
+}
+
+/// How updating each source went, shown as a table at the end
+struct Summary {
+    rows: Vec<(&'static str, String, Status)>,
+    /// Where progress is shown while updating
+    progress: Arc<Progress>,
+}
+
+impl Summary {
+    fn new(progress: Arc<Progress>) -> Summary {
+        Summary {
+            rows: Vec::new(),
+            progress,
+        }
+    }
+
+    /// Adds how a source went, showing it on the progress display
+    fn push(&mut self, kind: &'static str, name: String, status: Status) {
+        self.progress.event(Event::Finished(kind, &name, &status));
+        self.record(kind, name, status);
+    }
+
+    /// Adds how a source went, when the progress display already showed it
+    fn record(&mut self, kind: &'static str, name: String, status: Status) {
+        self.rows.push((kind, name, status));
+    }
+
+    /// How many sources failed
+    fn failed(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|(_, _, status)| matches!(status, Status::Failed(_)))
+            .count()
+    }
+
+    fn print(&self) {
+        let rows: Vec<[String; 4]> = self
+            .rows
+            .iter()
+            .map(|(kind, name, status)| {
+                let (status, details) = match status {
+                    Status::Updated(rev) => ("updated", rev.chars().take(12).collect()),
+                    Status::Failed(error) => ("failed", format!("{:#}", error)),
+                    Status::Skipped(reason) => ("skipped", String::from(*reason)),
+                    Status::Pruned => ("pruned", String::new()),
+                };
+                [kind.to_string(), name.clone(), status.into(), details]
+            })
+            .collect();
+        print_table(["KIND", "NAME", "STATUS", "DETAILS"], &rows);
+
+        let count = |wanted: &str| rows.iter().filter(|row| row[2] == wanted).count();
+        print!(
+            "{} updated, {} failed, {} skipped",
+            count("updated"),
+            count("failed"),
+            count("skipped")
+        );
+        match count("pruned") {
+            0 => println!(),
+            pruned => println!(", {} pruned", pruned),
+        }
+    }
+}
+
+/// A source to update
+struct Task {
+    name: String,
+    /// Where its files go
+    path: PathBuf,
+    cache: PathBuf,
+    source: Source,
+    rev: Option<String>,
+    clone_type: CloneType,
+}
+
+///Updates sources on a pool of workers, adding how it went to the summary
+///
+///# Arguments
+///* `kind` - What the sources have (lists, schemes or templates)
+///* `dir` - Base16 directory
+///* `target` - Directory where each source's directory goes
+///* `sources` - Sources to update, by name
+///* `clone_type` - Which files to get
+///* `pinning` - Which revision to update each source to
+///* `locked` - Locked sources of the same kind
+///* `options` - Concurrency, retries and whether to stop on failures
+///* `verbose` - Boolean, be verbose if true
+///* `summary` - How each source went
+///
+///Returns the locked sources. Sources that weren't updated keep what they had locked. Errors out
+///before updating any of them if they're pinned to the lockfile and some aren't on it.
+#[allow(clippy::too_many_arguments)]
+fn update_sources(
+    kind: &'static str,
+    dir: &Path,
+    target: &Path,
+    sources: Vec<(String, Source)>,
+    clone_type: CloneType,
+    pinning: &Pinning,
+    locked: &Locked,
+    options: &Options,
+    verbose: bool,
+    summary: &mut Summary,
+) -> Result<Locked> {
+    // Don't update anything if the lockfile can't be reproduced
+    if let Pinning::Locked = pinning {
+        check_locked(kind, &sources, locked)?;
+    }
+
+    let first_row = summary.rows.len();
+    summary.progress.event(Event::Queued(sources.len()));
+    let mut tasks = Vec::with_capacity(sources.len());
+    for (name, source) in sources {
+        if let Err(error) = check_name(&name) {
+            summary.push(kind, name, Status::Failed(error));
+        } else if !source.enabled {
+            summary.push(kind, name, Status::Skipped("disabled"));
+        } else if matches!(pinning, Pinning::Locked) && is_local_directory(&source) {
+            summary.push(kind, name, Status::Skipped("local directory"));
+        } else if options.fail_fast && summary.failed() > 0 {
+            summary.push(kind, name, Status::Skipped("another source failed"));
+        } else {
+            match pinning.rev(kind, locked, &name, &source.source) {
+                Ok(rev) => tasks.push((
+                    name.clone(),
+                    Task {
+                        path: target.join(&name),
+                        cache: cache_dir(dir, kind, &name),
+                        name,
+                        source,
+                        rev,
+                        clone_type,
+                    },
+                )),
+                Err(error) => summary.push(kind, name, Status::Failed(error)),
+            }
+        }
+    }
+
+    let retries = options.retries;
+    let progress = summary.progress.clone();
+    let results = pool::run(tasks, options.jobs, options.fail_fast, move |task: Task| {
+        progress.event(Event::Started(kind, &task.name));
+        let reporter = Reporter::new(progress.clone(), kind, &task.name);
+        // Only downloads are worth trying again
+        let retries = match task.source.local_path() {
+            Some(_) => 0,
+            None => retries,
+        };
+        let rev = pool::retry(
+            retries,
+            || {
+                fetch(
+                    &task.path,
+                    &task.cache,
+                    task.source.clone(),
+                    task.rev.clone(),
+                    verbose,
+                    task.clone_type,
+                    &reporter,
+                )
+            },
+            |error| progress.event(Event::Retrying(kind, &task.name, error)),
+        );
+
+        // The error itself goes to the summary
+        let status = match &rev {
+            Ok(rev) => Status::Updated(rev.clone()),
+            Err(error) => Status::Failed(anyhow!("{:#}", error)),
+        };
+        progress.event(Event::Finished(kind, &task.name, &status));
+        Ok(LockedSource {
+            source: task.source.source,
+            rev: rev?,
+        })
+    });
+
+    let mut new_locked = Locked::new();
+    for (name, result) in results {
+        if let (None | Some(Err(_)), Some(old)) = (&result, locked.get(&name)) {
+            new_locked.insert(name.clone(), old.clone());
+        }
+        match result {
+            Some(Ok(source)) => {
+                let status = Status::Updated(source.rev.clone());
+                new_locked.insert(name.clone(), source);
+                summary.record(kind, name, status);
+            }
+            Some(Err(error)) => summary.record(kind, name, Status::Failed(error)),
+            // Never started, so it wasn't shown yet
+            None => summary.push(kind, name, Status::Skipped("another source failed")),
+        }
+    }
+    summary.rows[first_row..].sort_by(|(_, a, _), (_, b, _)| a.cmp(b));
+    Ok(new_locked)
+}
+
+fn update_lists(
+    dir: &Path,
+    verbose: bool,
+    config_path: &Path,
+    pinning: &Pinning,
+    locked: &Locked,
+    options: &Options,
+    summary: &mut Summary,
+) -> Result<Locked> {
+    let sources_dir = &dir.join("sources");
+    if verbose {
+        println!("Updating sources list from sources.yaml")
+    }
+
+    // This section dealing with obtaining the config struct is copied directly from apply.rs
+    //Check if config file exists
+    if !config_path.exists() {
+        eprintln!("Config {:?} doesn't exist, creating", config_path);
+        let default_content = read_to_string("/etc/flavours.conf").unwrap_or_default();
+        let config_path_parent = config_path
+            .parent()
+            .with_context(|| format!("Couldn't get parent directory of {:?}", config_path))?;
+
+        create_dir_all(config_path_parent).with_context(|| {
+            format!(
+                "Couldn't create configuration file parent directory {:?}",
+                config_path_parent
+            )
+        })?;
+        write(config_path, default_content)
+            .with_context(|| format!("Couldn't create configuration file at {:?}", config_path))?;
+    }
+
+    let config_contents = read_to_string(config_path)
+        .with_context(|| format!("Couldn't read configuration file {:?}.", config_path))?;
+
+    let config = Config::read(&config_contents, config_path)?;
+
+    // Get schemes and templates repository from file
+    let (schemes_source, templates_source) = get_sources(&dir.join("sources.yaml"), &config)?;
+    if verbose {
+        println!("Schemes source: {}", schemes_source.source);
+        println!("Templates source: {}", templates_source.source);
+    }
+
+    let new_locked = update_sources(
+        "lists",
+        dir,
+        sources_dir,
+        vec![
+            (String::from("schemes"), schemes_source),
+            (String::from("templates"), templates_source),
+        ],
+        CloneType::List,
+        pinning,
+        locked,
+        options,
+        verbose,
+        summary,
+    )?;
+
+    // write additional config sources
+    let extras = [
+        (sources_dir.join("schemes"), config.extra_scheme),
+        (sources_dir.join("templates"), config.extra_template),
+    ];
+    for (list_dir, extra_sources) in extras.iter() {
+        let list_path = list_dir.join("list.yaml");
+        if let (Some(extra_sources), true) = (extra_sources, list_path.exists()) {
+            let mut list = read_list(&list_path)?;
+            for extra in extra_sources {
+                list.insert(extra.name.clone(), Source::from(extra.source.as_str()));
+            }
+            write_list(&list, &list_path)?;
+        }
+    }
+
+    Ok(new_locked)
+}
+
+///Checks if a source is a local directory, which can't be pinned: it's locked as `directory`, as
+///it has no revisions
+fn is_local_directory(source: &Source) -> bool {
+    source.local_path().map_or(false, |path| path.is_dir())
+}
+
+///Makes sure every source to update is on the lockfile, before updating any of them
+///
+///# Arguments
+///* `kind` - What the sources have (lists, schemes or templates)
+///* `sources` - Sources to update
+///* `locked` - Locked sources of the same kind
+fn check_locked(kind: &str, sources: &[(String, Source)], locked: &Locked) -> Result<()> {
+    let missing: Vec<String> = sources
+        .iter()
+        .filter(|(name, source)| source.enabled && check_name(name).is_ok())
+        .filter(|(_, source)| !is_local_directory(source))
+        .filter(|(name, source)| {
+            Pinning::Locked
+                .rev(kind, locked, name, &source.source)
+                .is_err()
+        })
+        .map(|(name, source)| format!("{}/{} ({})", kind, name, source.source))
+        .collect();
+    match missing.as_slice() {
+        [] => Ok(()),
+        missing => Err(anyhow!(
+            "These sources aren't on the lockfile: {}. Run update without --locked to add them.",
+            missing.join(", ")
+        )),
+    }
+}
+
+///Makes sure a source name is a single directory name, so its files can't end up outside of the
+///data directory
+fn check_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(anyhow!(
+            "Invalid source name '{}', it must be a single directory name",
+            name
+        )),
+    }
+}
+
+///Selects the sources matching any of the names (or glob patterns), all of them if there are none
+///
+///# Arguments
+///* `list` - Repository list
+///* `names` - Names or patterns to select
+fn select(list: &List, names: &[String]) -> Result<Vec<(String, Source)>> {
+    if names.is_empty() {
+        return Ok(list.clone().into_iter().collect());
+    }
+
+    let patterns = names
+        .iter()
+        .map(|name| Pattern::new(name).with_context(|| format!("Invalid pattern '{}'", name)))
+        .collect::<Result<Vec<_>>>()?;
+    for (pattern, name) in patterns.iter().zip(names) {
+        if !list.keys().any(|key| pattern.matches(key)) {
+            return Err(anyhow!("There's no source matching '{}' on the list", name));
+        }
+    }
+
+    Ok(list
+        .iter()
+        .filter(|(key, _)| patterns.iter().any(|pattern| pattern.matches(key)))
+        .map(|(key, source)| (key.clone(), source.clone()))
+        .collect())
+}
+
+///Removes the directories (and caches) of sources that are no longer on the list
+///
+///# Arguments
+///* `kind` - What the sources have (schemes or templates)
+///* `dir` - Base16 directory
+///* `target` - Directory with each source's directory
+///* `list` - Repository list
+///* `summary` - How each source went
+fn prune(
+    kind: &'static str,
+    dir: &Path,
+    target: &Path,
+    list: &List,
+    summary: &mut Summary,
+) -> Result<()> {
+    let entries = match read_dir(target) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    let mut pruned = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        // Generated schemes aren't on any list
+        let generated = kind == "schemes" && name == "generated";
+        if !list.contains_key(&name) && !generated && !name.starts_with('.') {
+            pruned.push(name);
+        }
+    }
+    pruned.sort();
+
+    for name in pruned {
+        let path = target.join(&name);
+        let is_symlink = path
+            .symlink_metadata()
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if is_symlink || path.is_file() {
+            remove_file(&path)
+        } else {
+            remove_dir_all(&path)
+        }
+        .with_context(|| format!("Couldn't remove {:?}", path))?;
+        let _ = remove_dir_all(cache_dir(dir, kind, &name));
+        summary.progress.event(Event::Queued(1));
+        summary.push(kind, name, Status::Pruned);
+    }
+    Ok(())
+}
+
+///Updates the sources on a repository list
+///
+///# Arguments
+///* `kind` - What the sources have (schemes or templates)
+///* `clone_type` - Which files to get
+///* `dir` - Base16 directory
+///* `names` - Only update sources with these names or patterns, all of them if empty
+///* `verbose` - Boolean, be verbose if true
+///* `pinning` - Which revision to update each source to
+///* `locked` - Locked sources of the same kind
+///* `options` - Concurrency, retries, pruning and whether to stop on failures
+///* `summary` - How each source went
+///
+///Returns the locked sources on the list
+#[allow(clippy::too_many_arguments)]
+fn update_from_list(
+    kind: &'static str,
+    clone_type: CloneType,
+    dir: &Path,
+    names: &[String],
+    verbose: bool,
+    pinning: &Pinning,
+    locked: &Locked,
+    options: &Options,
+    summary: &mut Summary,
+) -> Result<Locked> {
+    let list_path = &dir.join("sources").join(kind).join("list.yaml");
+    let target = &dir.join(kind);
+
+    if verbose {
+        println!("Updating {} from source", kind)
+    }
+    let list = read_list(list_path)?;
+    let selected = select(&list, names)?;
+
+    // Sources that weren't selected keep what they had locked
+    let mut new_locked: Locked = locked
+        .iter()
+        .filter(|(name, _)| list.contains_key(*name))
+        .map(|(name, source)| (name.clone(), source.clone()))
+        .collect();
+    new_locked.extend(update_sources(
+        kind, dir, target, selected, clone_type, pinning, locked, options, verbose, summary,
+    )?);
+
+    if options.prune {
+        prune(kind, dir, target, &list, summary)?;
+    }
+    Ok(new_locked)
+}
+
+///Implementation of update operation
+///
+///Sources that fail to update don't stop the others, a table with how each source went is shown
+///at the end.
+///
+///# Arguments
+///* `operation` - Which operation to do
+///* `names` - Only update schemes or templates with these names or patterns, all of them if empty
+///* `dir` - The base path to be used
+///* `verbose` - Boolean, be verbose if true
+///* `config_path` - Flavours configuration path, the lockfile lives next to it
+///* `pinning` - Which revision to update each source to
+///* `options` - Concurrency, retries and whether to stop on failures
+pub fn update(
+    operation: &str,
+    names: &[String],
+    dir: &Path,
+    verbose: bool,
+    config_path: &Path,
+    pinning: Pinning,
+    options: &Options,
+) -> Result<()> {
+    let lock_path = Lockfile::path(config_path);
+    if matches!(pinning, Pinning::Locked) && !lock_path.exists() {
+        return Err(anyhow!(
+            "There's no lockfile at {:?}. Run update without --locked to create it.",
+            lock_path
+        ));
+    }
+
+    let base16_dir = &dir.join("base16");
+    create_dir_all(base16_dir)?;
+
+    let mut lock = Lockfile::read(&lock_path)?;
+
+    let (lists, schemes, templates) = match operation {
+        "lists" => (true, false, false),
+        "schemes" => (false, true, false),
+        "templates" => (false, false, true),
+        "all" => (true, true, true),
+        _ => return Err(anyhow!("Invalid operation")),
+    };
+    if !names.is_empty() && !matches!(operation, "schemes" | "templates") {
+        return Err(anyhow!(
+            "Sources can only be picked by name when updating schemes or templates"
+        ));
+    }
+
+    // Don't change anything if there's nothing to bump
+    if let Pinning::Bump(bumped) = pinning {
+        check_bump(bumped, base16_dir, &lock)?;
+    }
+
+    // Verbose shows a line per event, with git's output between them
+    let mut summary = Summary::new(Arc::new(Progress::new(verbose)));
+    if lists {
+        lock.lists = update_lists(
+            base16_dir,
+            verbose,
+            config_path,
+            &pinning,
+            &lock.lists,
+            options,
+            &mut summary,
+        )?;
+    }
+    if schemes {
+        lock.schemes = update_from_list(
+            "schemes",
+            CloneType::Scheme,
+            base16_dir,
+            names,
+            verbose,
+            &pinning,
+            &lock.schemes,
+            options,
+            &mut summary,
+        )?;
+    }
+    if templates {
+        lock.templates = update_from_list(
+            "templates",
+            CloneType::Template,
+            base16_dir,
+            names,
+            verbose,
+            &pinning,
+            &lock.templates,
+            options,
+            &mut summary,
+        )?;
+    }
+    summary.progress.finish();
+    summary.print();
+
+    // Nothing changed when locked
+    if !matches!(pinning, Pinning::Locked) {
+        lock.write(&lock_path)?;
+    }
+
+    // Not getting every locked revision isn't a reproduced update
+    let locked = matches!(pinning, Pinning::Locked);
+    match summary.failed() {
+        failed if failed > 0 && (options.fail_fast || locked) => {
+            Err(anyhow!("{} source(s) failed to update", failed))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(names: &[&str]) -> List {
+        names
+            .iter()
+            .map(|name| (name.to_string(), Source::from("https://example.com")))
+            .collect()
+    }
+
+    fn selected(list: &List, names: &[&str]) -> Result<Vec<String>> {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        Ok(select(list, &names)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    #[test]
+    fn select_by_name_or_pattern() {
+        let list = list(&["alacritty", "gruvbox", "gruvbox-material", "kitty"]);
+        assert_eq!(selected(&list, &[]).unwrap().len(), 4);
+        assert_eq!(
+            selected(&list, &["kitty", "alacritty"]).unwrap(),
+            ["alacritty", "kitty"]
+        );
+        assert_eq!(
+            selected(&list, &["gruvbox*"]).unwrap(),
+            ["gruvbox", "gruvbox-material"]
+        );
+        // Matching more than once doesn't repeat it
+        assert_eq!(
+            selected(&list, &["gruvbox", "gruv*x"]).unwrap(),
+            ["gruvbox"]
+        );
+    }
+
+    #[test]
+    fn select_missing() {
+        let list = list(&["kitty"]);
+        let error = selected(&list, &["kitty", "vim*"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "There's no source matching 'vim*' on the list"
+        );
+        assert!(selected(&list, &["[kitty"]).is_err());
+    }
+
+    #[test]
+    fn source_names() {
+        assert!(check_name("base16-vim").is_ok());
+        assert!(check_name("with.dots").is_ok());
+        for name in ["", "..", "../../x", "a/b", "/tmp/x", "."] {
+            assert!(check_name(name).is_err(), "{:?} should be rejected", name);
+        }
+    }
+
+    #[test]
+    fn prune_removed_sources() {
+        let dir = std::env::temp_dir().join(format!("flavours-prune-{}", std::process::id()));
+        let target = dir.join("schemes");
+        for name in ["kept", "removed", "generated", ".hidden"] {
+            create_dir_all(target.join(name)).unwrap();
+        }
+        write(target.join("stray.yaml"), "").unwrap();
+        let cache = cache_dir(&dir, "schemes", "removed");
+        create_dir_all(&cache).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(target.join("kept"), target.join("linked")).unwrap();
+
+        let mut summary = Summary::new(Arc::new(Progress::new(true)));
+        prune("schemes", &dir, &target, &list(&["kept"]), &mut summary).unwrap();
+
+        let mut left: Vec<String> = read_dir(&target)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        let pruned: Vec<&str> = summary
+            .rows
+            .iter()
+            .map(|(_, name, _)| name.as_str())
+            .collect();
+        let cache_removed = !cache.exists();
+        // The symlink is removed, not what it points to
+        let kept_intact = target.join("kept").is_dir();
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(left, [".hidden", "generated", "kept"]);
+        #[cfg(unix)]
+        assert_eq!(pruned, ["linked", "removed", "stray.yaml"]);
+        assert!(cache_removed);
+        assert!(kept_intact);
+    }
+
+    fn options() -> Options {
+        Options {
+            jobs: 1,
+            retries: 0,
+            fail_fast: false,
+            prune: false,
+        }
+    }
+
+    #[test]
+    fn locked_without_lockfile() {
+        let dir = std::env::temp_dir().join(format!("flavours-nolock-{}", std::process::id()));
+        let config = dir.join("config.toml");
+        let error = update(
+            "all",
+            &[],
+            &dir,
+            false,
+            &config,
+            Pinning::Locked,
+            &options(),
+        );
+        let created = dir.exists();
+
+        assert!(error
+            .unwrap_err()
+            .to_string()
+            .starts_with("There's no lockfile"));
+        assert!(!created);
+    }
+
+    #[test]
+    fn locked_missing_entries() {
+        let lock = |source: &str| LockedSource {
+            source: source.to_string(),
+            rev: String::from("abc"),
+        };
+        let mut locked = Locked::new();
+        locked.insert(String::from("kept"), lock("https://example.com"));
+        locked.insert(String::from("moved"), lock("https://example.org"));
+        let mut disabled = Source::from("https://example.com");
+        disabled.enabled = false;
+        let sources: Vec<(String, Source)> = vec![
+            (String::from("kept"), Source::from("https://example.com")),
+            (String::from("moved"), Source::from("https://example.com")),
+            (String::from("new"), Source::from("https://example.com")),
+            (String::from("disabled"), disabled),
+        ];
+
+        assert_eq!(
+            check_locked("schemes", &sources, &locked)
+                .unwrap_err()
+                .to_string(),
+            "These sources aren't on the lockfile: schemes/moved (https://example.com), \
+             schemes/new (https://example.com). Run update without --locked to add them."
+        );
+        assert!(check_locked("schemes", &sources[..1], &locked).is_ok());
+    }
+
+    #[test]
+    fn locked_failures_error() {
+        let dir = std::env::temp_dir().join(format!("flavours-lockfail-{}", std::process::id()));
+        let lists = dir.join("base16").join("sources").join("templates");
+        create_dir_all(&lists).unwrap();
+        let archive = dir.join("gone.tar.gz").to_string_lossy().into_owned();
+        let mut list = List::new();
+        list.insert(String::from("gone"), Source::from(archive.as_str()));
+        write_list(&list, &lists.join("list.yaml")).unwrap();
+        let config = dir.join("config.toml");
+        let mut lock = Lockfile::default();
+        lock.templates.insert(
+            String::from("gone"),
+            LockedSource {
+                source: archive,
+                rev: String::from("sha256:00"),
+            },
+        );
+        lock.write(&Lockfile::path(&config)).unwrap();
+
+        let result = update(
+            "templates",
+            &[],
+            &dir,
+            false,
+            &config,
+            Pinning::Locked,
+            &options(),
+        );
+        let unlocked = update(
+            "templates",
+            &[],
+            &dir,
+            false,
+            &config,
+            Pinning::Bump("gone"),
+            &options(),
+        );
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "1 source(s) failed to update"
+        );
+        // Without --locked, failures only show on the summary
+        assert!(unlocked.is_ok());
+    }
+
+    #[test]
+    fn locked_skips_local_directories() {
+        let dir = std::env::temp_dir().join(format!("flavours-lockdir-{}", std::process::id()));
+        let lists = dir.join("base16").join("sources").join("templates");
+        let local = dir.join("local");
+        create_dir_all(&lists).unwrap();
+        create_dir_all(local.join("templates")).unwrap();
+        write(local.join("templates").join("default.mustache"), "").unwrap();
+        let mut list = List::new();
+        let source = Source::from(local.to_string_lossy().as_ref());
+        list.insert(String::from("local"), source.clone());
+        write_list(&list, &lists.join("list.yaml")).unwrap();
+        let config = dir.join("config.toml");
+        Lockfile::default().write(&Lockfile::path(&config)).unwrap();
+
+        let checked = check_locked(
+            "templates",
+            &[(String::from("local"), source)],
+            &Locked::new(),
+        );
+        let result = update(
+            "templates",
+            &[],
+            &dir,
+            false,
+            &config,
+            Pinning::Locked,
+            &options(),
+        );
+        let updated = dir.join("base16").join("templates").join("local").exists();
+        remove_dir_all(&dir).unwrap();
+
+        assert!(checked.is_ok());
+        assert!(result.is_ok());
+        assert!(!updated);
+    }
+}
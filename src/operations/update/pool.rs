@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+///Runs jobs on a bounded pool of worker threads
+///
+///A job that fails (or panics) doesn't stop the others, unless `fail_fast` is set: then jobs that
+///didn't start yet are skipped.
+///
+///# Arguments
+///* `jobs` - Named jobs to run
+///* `workers` - How many jobs to run at once
+///* `fail_fast` - Skip the remaining jobs after one fails
+///* `work` - What to do with each job
+///
+///Returns each job's name and result, in the same order they were given, none if it was skipped
+pub fn run<T, R, F>(
+    jobs: Vec<(String, T)>,
+    workers: usize,
+    fail_fast: bool,
+    work: F,
+) -> Vec<(String, Option<Result<R>>)>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Result<R> + Send + Sync + 'static,
+{
+    let names: Vec<String> = jobs.iter().map(|(name, _)| name.clone()).collect();
+    let queue: VecDeque<(usize, T)> = jobs.into_iter().map(|(_, job)| job).enumerate().collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let stop = Arc::new(AtomicBool::new(false));
+    let work = Arc::new(work);
+    let (sender, receiver) = mpsc::channel();
+
+    let workers: Vec<_> = (0..workers.clamp(1, names.len().max(1)))
+        .map(|_| {
+            let (queue, stop, work, sender) =
+                (queue.clone(), stop.clone(), work.clone(), sender.clone());
+            spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    // Lock only while taking the next job
+                    let next = queue.lock().map(|mut queue| queue.pop_front());
+                    let (index, job) = match next {
+                        Ok(Some(next)) => next,
+                        _ => break,
+                    };
+                    let result = catch_unwind(AssertUnwindSafe(|| work(job)))
+                        .unwrap_or_else(|_| Err(anyhow!("Panicked while updating")));
+                    if result.is_err() && fail_fast {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                    if sender.send((index, result)).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(sender);
+
+    let mut results: Vec<Option<Result<R>>> = names.iter().map(|_| None).collect();
+    for (index, result) in receiver {
+        results[index] = Some(result);
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    names.into_iter().zip(results).collect()
+}
+
+/// How many times the wait between retries doubles, at most (up to 16 seconds)
+const MAX_BACKOFF: u32 = 5;
+
+///Runs something, trying again (waiting longer each time) if it fails
+///
+///# Arguments
+///* `retries` - How many times to try again
+///* `attempt` - What to run
+///* `on_retry` - Called with the error, before trying again
+pub fn retry<R>(
+    retries: u32,
+    attempt: impl Fn() -> Result<R>,
+    on_retry: impl Fn(&anyhow::Error),
+) -> Result<R> {
+    let mut tries = 0;
+    loop {
+        match attempt() {
+            Err(error) if tries < retries => {
+                on_retry(&error);
+                sleep(Duration::from_millis(500 << tries.min(MAX_BACKOFF)));
+                tries += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use super::Status;
+
+/// Something that happened while updating sources
+pub enum Event<'a> {
+    /// This many more sources are going to be updated
+    Queued(usize),
+    /// A source (kind and name) started updating
+    Started(&'a str, &'a str),
+    /// A source (kind and name) moved on to another phase, such as fetching or checking out
+    Phase(&'a str, &'a str, &'a str),
+    /// How the current phase of a source (kind and name) is going, such as object or file counts
+    Progress(&'a str, &'a str, &'a str),
+    /// A line of output (such as git's) from a source (kind and name), shown when verbose
+    Output(&'a str, &'a str, &'a str),
+    /// A source (kind and name) failed, and is going to be tried again
+    Retrying(&'a str, &'a str, &'a anyhow::Error),
+    /// A source (kind and name) is done
+    Finished(&'a str, &'a str, &'a Status),
+}
+
+/// How progress is shown
+enum Display {
+    /// A bar with the total progress, and a line for each source being updated
+    Bars {
+        multi: MultiProgress,
+        total: ProgressBar,
+        /// Spinner and current phase of each source being updated
+        sources: Mutex<HashMap<String, (ProgressBar, String)>>,
+    },
+    /// A line for each event, written to stderr
+    Lines(Mutex<Box<dyn Write + Send>>),
+}
+
+/// Shows how an update is going, with progress bars on terminals and a line per event otherwise
+pub struct Progress {
+    display: Display,
+    total: AtomicUsize,
+    done: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+impl Progress {
+    ///Creates the progress display, using bars if stderr is a terminal
+    ///
+    ///# Arguments
+    ///* `lines` - Use a line per event even on terminals, so other output doesn't get mixed in
+    pub fn new(lines: bool) -> Progress {
+        let target = ProgressDrawTarget::stderr();
+        if lines || target.is_hidden() {
+            return Progress::to_writer(Box::new(io::stderr()));
+        }
+
+        let multi = MultiProgress::with_draw_target(target);
+        let total = multi.add(
+            ProgressBar::new(0).with_style(
+                ProgressStyle::with_template("{bar:40} {pos}/{len} {msg}")
+                    .expect("Progress template is valid"),
+            ),
+        );
+        Progress::with_display(Display::Bars {
+            multi,
+            total,
+            sources: Mutex::new(HashMap::new()),
+        })
+    }
+
+    ///Creates a progress display with a line per event, written to `writer`
+    fn to_writer(writer: Box<dyn Write + Send>) -> Progress {
+        Progress::with_display(Display::Lines(Mutex::new(writer)))
+    }
+
+    fn with_display(display: Display) -> Progress {
+        Progress {
+            display,
+            total: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+        }
+    }
+
+    ///Shows a line, above the bars if there are any
+    fn println(&self, line: &str) {
+        match &self.display {
+            Display::Bars { multi, .. } => {
+                let _ = multi.println(line);
+            }
+            Display::Lines(writer) => {
+                if let Ok(mut writer) = writer.lock() {
+                    let _ = writeln!(writer, "{}", line);
+                }
+            }
+        }
+    }
+
+    ///Shows an event
+    pub fn event(&self, event: Event) {
+        match event {
+            Event::Queued(count) => {
+                let total = self.total.fetch_add(count, Ordering::SeqCst) + count;
+                if let Display::Bars { total: bar, .. } = &self.display {
+                    bar.set_length(total as u64);
+                }
+            }
+            Event::Started(kind, name) => match &self.display {
+                Display::Bars {
+                    multi,
+                    total,
+                    sources,
+                } => {
+                    let spinner = multi.insert_before(
+                        total,
+                        ProgressBar::new_spinner().with_message(format!("{} {}", kind, name)),
+                    );
+                    spinner.enable_steady_tick(Duration::from_millis(100));
+                    if let Ok(mut sources) = sources.lock() {
+                        sources.insert(format!("{}/{}", kind, name), (spinner, String::new()));
+                    }
+                }
+                Display::Lines(_) => self.println(&format!("{} {}: updating", kind, name)),
+            },
+            Event::Phase(kind, name, phase) => match &self.display {
+                Display::Bars { sources, .. } => {
+                    if let Some((spinner, current)) = sources
+                        .lock()
+                        .ok()
+                        .as_mut()
+                        .and_then(|sources| sources.get_mut(&format!("{}/{}", kind, name)))
+                    {
+                        *current = phase.to_string();
+                        spinner.set_message(format!("{} {}: {}", kind, name, phase));
+                    }
+                }
+                Display::Lines(_) => self.println(&format!("{} {}: {}", kind, name, phase)),
+            },
+            // Counts change too often to get a line each
+            Event::Progress(kind, name, progress) => {
+                if let Display::Bars { sources, .. } = &self.display {
+                    if let Some((spinner, phase)) = sources
+                        .lock()
+                        .ok()
+                        .as_ref()
+                        .and_then(|sources| sources.get(&format!("{}/{}", kind, name)))
+                    {
+                        spinner.set_message(format!("{} {}: {} ({})", kind, name, phase, progress));
+                    }
+                }
+            }
+            Event::Output(kind, name, line) => {
+                self.println(&format!("{} {}: {}", kind, name, line))
+            }
+            Event::Retrying(kind, name, error) => {
+                self.println(&format!("{} {}: retrying ({:#})", kind, name, error))
+            }
+            Event::Finished(kind, name, status) => {
+                let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Status::Failed(_) = status {
+                    self.failed.fetch_add(1, Ordering::SeqCst);
+                }
+                match &self.display {
+                    Display::Bars { total, sources, .. } => {
+                        let spinner = sources
+                            .lock()
+                            .ok()
+                            .and_then(|mut sources| sources.remove(&format!("{}/{}", kind, name)));
+                        if let Some((spinner, _)) = spinner {
+                            spinner.finish_and_clear();
+                        }
+                        total.set_position(done as u64);
+                        match self.failed.load(Ordering::SeqCst) {
+                            0 => total.set_message(""),
+                            failed => total.set_message(format!("({} failed)", failed)),
+                        }
+                    }
+                    Display::Lines(_) => {
+                        let outcome = match status {
+                            Status::Updated(rev) => {
+                                format!("updated to {}", rev.chars().take(12).collect::<String>())
+                            }
+                            Status::Failed(error) => format!("failed ({:#})", error),
+                            Status::Skipped(reason) => format!("skipped ({})", reason),
+                            Status::Pruned => String::from("pruned"),
+                        };
+                        self.println(&format!(
+                            "[{}/{}] {} {}: {}",
+                            done,
+                            self.total.load(Ordering::SeqCst),
+                            kind,
+                            name,
+                            outcome
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    ///Removes the progress bars, before showing the summary
+    pub fn finish(&self) {
+        if let Display::Bars { total, .. } = &self.display {
+            total.finish_and_clear();
+        }
+    }
+}
+
+/// Tells the progress display how a single source is going, from the worker updating it
+#[derive(Clone)]
+pub struct Reporter {
+    progress: Arc<Progress>,
+    kind: &'static str,
+    name: String,
+}
+
+impl Reporter {
+    ///Creates a reporter for a source
+    ///
+    ///# Arguments
+    ///* `progress` - Where progress is shown
+    ///* `kind` - What the source has (schemes or templates)
+    ///* `name` - Source name
+    pub fn new(progress: Arc<Progress>, kind: &'static str, name: &str) -> Reporter {
+        Reporter {
+            progress,
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    ///Shows that the source moved on to another phase
+    pub fn phase(&self, phase: &str) {
+        self.progress
+            .event(Event::Phase(self.kind, &self.name, phase));
+    }
+
+    ///Shows how the current phase is going
+    pub fn progress(&self, progress: &str) {
+        self.progress
+            .event(Event::Progress(self.kind, &self.name, progress));
+    }
+
+    ///Shows a line of output, prefixed with the source it's from
+    pub fn output(&self, line: &str) {
+        self.progress
+            .event(Event::Output(self.kind, &self.name, line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::spawn;
+
+    /// Lines written by the progress display, shared with the test
+    #[derive(Clone, Default)]
+    struct Written(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Written {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lines_from_two_sources() {
+        let written = Written::default();
+        let progress = Arc::new(Progress::to_writer(Box::new(written.clone())));
+        progress.event(Event::Queued(2));
+
+        let workers: Vec<_> = ["alacritty", "kitty"]
+            .iter()
+            .map(|name| {
+                let reporter = Reporter::new(progress.clone(), "templates", name);
+                spawn(move || {
+                    reporter.phase("fetching");
+                    reporter.progress("Receiving objects: 50% (1/2)");
+                    for line in 0..50 {
+                        reporter.output(&format!("line {}", line));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        progress.event(Event::Finished(
+            "templates",
+            "kitty",
+            &Status::Skipped("disabled"),
+        ));
+
+        let written = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        for name in ["alacritty", "kitty"] {
+            let prefix = format!("templates {}: ", name);
+            let from_source: Vec<&str> = lines
+                .iter()
+                .filter_map(|line| line.strip_prefix(&prefix))
+                .collect();
+            // Every line whole, in order, and counts don't get a line
+            let mut expected = vec![String::from("fetching")];
+            expected.extend((0..50).map(|line| format!("line {}", line)));
+            assert_eq!(from_source, expected);
+        }
+        assert_eq!(lines.len(), 2 * 51 + 1);
+        assert_eq!(
+            lines.last().unwrap(),
+            &"[1/2] templates kitty: skipped (disabled)"
+        );
+    }
+}
@@ -1,4 +1,7 @@
+use anyhow::{anyhow, Context, Result};
+use base16_color_scheme::scheme::{BaseIndex, RgbColor};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::PathBuf;
 
@@ -38,3 +41,47 @@ impl fmt::Display for Luminance {
         }
     }
 }
+
+/// Scheme on tinted-theming's format, with its colors under `palette`
+#[derive(Deserialize)]
+struct TintedScheme {
+    name: String,
+    #[serde(default)]
+    author: String,
+    palette: BTreeMap<BaseIndex, String>,
+}
+
+/// Parse a base16 scheme, either on the classic format or on tinted-theming's (with `name`,
+/// and the colors under `palette`)
+///
+/// * `contents` - Scheme file contents
+pub fn parse(contents: &str) -> Result<base16_color_scheme::Scheme> {
+    let error = match serde_yaml::from_str(contents) {
+        Ok(scheme) => return Ok(scheme),
+        Err(error) => error,
+    };
+    let tinted: TintedScheme = match serde_yaml::from_str(contents) {
+        Ok(tinted) => tinted,
+        // Report why it isn't a classic scheme, the most common format
+        Err(_) => return Err(error.into()),
+    };
+
+    let colors = tinted
+        .palette
+        .into_iter()
+        .map(|(index, color)| {
+            let mut rgb = [0; 3];
+            hex::decode_to_slice(color.trim_start_matches('#'), &mut rgb)
+                .map_err(|_| anyhow!("Invalid color {:?} on palette", color))?;
+            Ok((index, RgbColor(rgb)))
+        })
+        .collect::<Result<_>>()
+        .context("Couldn't parse scheme palette")?;
+
+    Ok(base16_color_scheme::Scheme {
+        scheme: tinted.name,
+        author: tinted.author,
+        slug: String::new(),
+        colors,
+    })
+}
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Context, Result};
+use serde::de::{self, value::MapAccessDeserializer, Deserializer, MapAccess, Visitor};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::ops::Not;
+use std::path::{Component, Path, PathBuf};
+
+/// Default schemes list repository
+pub const DEFAULT_SCHEMES: &str = "https://github.com/chriskempson/base16-schemes-source.git";
+/// Default templates list repository
+pub const DEFAULT_TEMPLATES: &str = "https://github.com/chriskempson/base16-templates-source.git";
+
+/// Repository list (`list.yaml`), mapping each name to its source
+pub type List = BTreeMap<String, Source>;
+
+/// Structure for `sources.yaml`, with the schemes and templates lists
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Sources {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schemes: Option<Source>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Source>,
+}
+
+/// A source repository, either just its url or a map with more details
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// Where the repository is fetched from
+    pub source: String,
+    /// Branch or tag to fetch, defaults to the repository's HEAD
+    pub reference: Option<String>,
+    /// Subdirectory of the repository with the files
+    pub path: Option<String>,
+    /// Only get schemes inside these subdirectories (of `path`), for scheme collections
+    pub subdirectories: Option<Vec<String>>,
+    /// Whether to fetch the repository at all
+    pub enabled: bool,
+    /// Symlink a local directory, instead of copying it
+    pub link: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl From<&str> for Source {
+    fn from(source: &str) -> Source {
+        Source {
+            source: source.into(),
+            reference: None,
+            path: None,
+            subdirectories: None,
+            enabled: true,
+            link: false,
+        }
+    }
+}
+
+impl Source {
+    /// Whether there's nothing but the url, so it can be written as a plain string
+    fn is_plain(&self) -> bool {
+        self.reference.is_none()
+            && self.path.is_none()
+            && self.subdirectories.is_none()
+            && self.enabled
+            && !self.link
+    }
+
+    /// Get the path of a local source (a directory or archive), none if it's a git repository
+    ///
+    /// Local sources are absolute paths (after expanding `~` and environment variables), or
+    /// relative ones starting with `./` or `../`. Use `file://` urls for local git repositories.
+    pub fn local_path(&self) -> Option<PathBuf> {
+        let expanded = shellexpand::full(&self.source)
+            .map(|expanded| expanded.into_owned())
+            .unwrap_or_else(|_| self.source.clone());
+        let path = PathBuf::from(expanded);
+        if path.is_absolute() || self.source.starts_with("./") || self.source.starts_with("../") {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Get the subdirectory, making sure it stays inside the repository
+    pub fn subdirectory(&self) -> Result<Option<&Path>> {
+        self.path
+            .as_deref()
+            .map(|path| self.inside(path))
+            .transpose()
+    }
+
+    /// Get the scheme subdirectories to import (relative to the subdirectory), empty for all
+    pub fn subdirectories(&self) -> Result<Vec<&Path>> {
+        self.subdirectories
+            .iter()
+            .flatten()
+            .map(|subdirectory| self.inside(subdirectory))
+            .collect()
+    }
+
+    /// Make sure a path is relative, and inside the repository
+    fn inside<'a>(&self, path: &'a str) -> Result<&'a Path> {
+        let path = Path::new(path);
+        if path.components().all(|c| matches!(c, Component::Normal(_))) {
+            Ok(path)
+        } else {
+            Err(anyhow!(
+                "Path {:?} of '{}' must be relative, and inside the repository",
+                path,
+                self.source
+            ))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Source {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SourceVisitor;
+
+        impl<'de> Visitor<'de> for SourceVisitor {
+            type Value = Source;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a repository url, or a map with its source")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Source, E> {
+                Ok(Source::from(value))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Source, A::Error> {
+                DetailedSource::deserialize(MapAccessDeserializer::new(map)).map(|d| d.0)
+            }
+        }
+
+        deserializer.deserialize_any(SourceVisitor)
+    }
+}
+
+impl Serialize for Source {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_plain() {
+            return serializer.serialize_str(&self.source);
+        }
+
+        #[derive(Serialize)]
+        struct Detailed<'a> {
+            source: &'a str,
+            #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+            reference: &'a Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            path: &'a Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            subdirectories: &'a Option<Vec<String>>,
+            #[serde(skip_serializing_if = "is_enabled")]
+            enabled: bool,
+            #[serde(skip_serializing_if = "Not::not")]
+            link: bool,
+        }
+        Detailed {
+            source: &self.source,
+            reference: &self.reference,
+            path: &self.path,
+            subdirectories: &self.subdirectories,
+            enabled: self.enabled,
+            link: self.link,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Map form of a source, using the derived implementation
+struct DetailedSource(Source);
+
+impl<'de> Deserialize<'de> for DetailedSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(remote = "Source", deny_unknown_fields)]
+        struct Remote {
+            #[serde(alias = "url")]
+            source: String,
+            #[serde(default, rename = "ref", alias = "branch")]
+            reference: Option<String>,
+            #[serde(default)]
+            path: Option<String>,
+            #[serde(default)]
+            subdirectories: Option<Vec<String>>,
+            #[serde(default = "enabled")]
+            enabled: bool,
+            #[serde(default)]
+            link: bool,
+        }
+        Remote::deserialize(deserializer).map(DetailedSource)
+    }
+}
+
+///Parses a YAML file, an empty one being the default value
+fn read_yaml<T>(path: &Path) -> Result<T>
+where
+    T: for<'de> Deserialize<'de> + Default,
+{
+    let contents = fs::read_to_string(path).with_context(|| format!("Couldn't read {:?}", path))?;
+    let parsed: Option<T> =
+        serde_yaml::from_str(&contents).with_context(|| format!("Couldn't parse {:?}", path))?;
+    Ok(parsed.unwrap_or_default())
+}
+
+impl Sources {
+    ///Reads `sources.yaml`, none if it doesn't exist
+    pub fn read(path: &Path) -> Result<Option<Sources>> {
+        if path.exists() {
+            read_yaml(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    ///Writes `sources.yaml`
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_yaml::to_string(self)?)
+            .with_context(|| format!("Couldn't write {:?}", path))
+    }
+}
+
+///Reads a repository list
+pub fn read_list(path: &Path) -> Result<List> {
+    if !path.exists() {
+        return Err(anyhow!(
+            "Failed to read repository list {:?}. Try 'update lists' first?",
+            path
+        ));
+    }
+    read_yaml(path)
+}
+
+///Writes a repository list
+pub fn write_list(list: &List, path: &Path) -> Result<()> {
+    fs::write(path, serde_yaml::to_string(list)?)
+        .with_context(|| format!("Couldn't write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<List, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    #[test]
+    fn plain_string() {
+        let list = parse("gruvbox: https://example.com/gruvbox.git").unwrap();
+        assert_eq!(
+            list["gruvbox"],
+            Source::from("https://example.com/gruvbox.git")
+        );
+    }
+
+    #[test]
+    fn detailed_map() {
+        let list = parse(
+            "collection:\n  source: https://example.com/schemes.git\n  ref: v1\n  path: base16\n  subdirectories: [dark, light]\n  enabled: false\n  link: true\n",
+        )
+        .unwrap();
+        assert_eq!(
+            list["collection"],
+            Source {
+                source: "https://example.com/schemes.git".into(),
+                reference: Some("v1".into()),
+                path: Some("base16".into()),
+                subdirectories: Some(vec!["dark".into(), "light".into()]),
+                enabled: false,
+                link: true,
+            }
+        );
+    }
+
+    #[test]
+    fn defaults_and_aliases() {
+        let list = parse("a:\n  url: https://example.com/a.git\n  branch: main\n").unwrap();
+        assert_eq!(list["a"].source, "https://example.com/a.git");
+        assert_eq!(list["a"].reference.as_deref(), Some("main"));
+        assert!(list["a"].enabled);
+        assert!(!list["a"].link);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let error = parse("a:\n  source: https://example.com/a.git\n  brnach: main\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown field `brnach`"), "{}", error);
+        assert!(parse("a:\n  ref: main\n").is_err());
+        assert!(parse("a: [https://example.com/a.git]").is_err());
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        let mut list = List::new();
+        list.insert(
+            "plain".into(),
+            Source::from("https://example.com/plain.git"),
+        );
+        list.insert(
+            "detailed".into(),
+            Source {
+                reference: Some("v1".into()),
+                enabled: false,
+                ..Source::from("https://example.com/detailed.git")
+            },
+        );
+
+        let yaml = serde_yaml::to_string(&list).unwrap();
+        assert_eq!(
+            yaml,
+            "detailed:\n  source: https://example.com/detailed.git\n  ref: v1\n  enabled: false\nplain: https://example.com/plain.git\n"
+        );
+        assert_eq!(parse(&yaml).unwrap(), list);
+    }
+
+    #[test]
+    fn error_line() {
+        let path = std::env::temp_dir().join(format!("flavours-list-{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            "# comment\na: https://example.com/a.git\nb:\n  source: x\n  nope: 1\n",
+        )
+        .unwrap();
+        let error = format!("{:#}", read_list(&path).unwrap_err());
+        fs::remove_file(&path).unwrap();
+        assert!(error.contains("line 5"), "{}", error);
+    }
+
+    #[test]
+    fn empty_list() {
+        let path = std::env::temp_dir().join(format!("flavours-empty-{}.yaml", std::process::id()));
+        fs::write(&path, "# Nothing yet\n").unwrap();
+        let list = read_list(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(list.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! Golden-file tests for scheme generation
+//!
+//! Generates schemes from the sample images with every algorithm and mode, and compares them with
+//! the ones stored in `tests/golden`. Run with `UPDATE_GOLDEN=1` to write them again, after
+//! intentionally changing the generated output.
+
+use anyhow::{Context, Result};
+use flavours::operations::generate::{self, extraction, ImageOptions, MinContrast, Mode};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const IMAGES: [&str; 3] = ["sunset", "flat", "forest"];
+
+fn generate_scheme(image: &str, algorithm: &str, mode: Option<Mode>) -> Result<String> {
+    let image_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("images")
+        .join(format!("{}.png", image));
+
+    let extractor = extraction::extractor(algorithm, 0)?;
+    let palette = generate::extract_palette(
+        &[image_path],
+        &ImageOptions::default(),
+        extractor.as_ref(),
+        15,
+        1,
+    )?;
+    let mode = match mode {
+        Some(mode) => mode,
+        None => generate::detect_mode(&palette, false)?,
+    };
+    let colors = generate::generate_from_palette(&palette, mode, &MinContrast::default(), false)?;
+    let scheme = generate::build_scheme(colors, image.into(), image.into(), "flavours".into())?;
+
+    Ok(serde_yaml::to_string(&scheme)?)
+}
+
+fn check_golden(name: &str, generated: &str) -> Result<()> {
+    let golden_path: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.yaml", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, generated)?;
+        return Ok(());
+    }
+
+    let golden = fs::read_to_string(&golden_path)
+        .with_context(|| format!("Couldn't read golden file {:?}", golden_path))?;
+    assert_eq!(
+        golden, generated,
+        "Generated scheme differs from golden file {:?}",
+        golden_path
+    );
+
+    Ok(())
+}
+
+#[test]
+fn generated_schemes_match_golden_files() -> Result<()> {
+    for image in IMAGES {
+        for algorithm in extraction::ALGORITHMS {
+            for (mode_name, mode) in [
+                ("auto", None),
+                ("dark", Some(Mode::Dark)),
+                ("light", Some(Mode::Light)),
+            ] {
+                let generated = generate_scheme(image, algorithm, mode)?;
+                check_golden(
+                    &format!("{}-{}-{}", image, algorithm, mode_name),
+                    &generated,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn generation_is_deterministic() -> Result<()> {
+    for algorithm in extraction::ALGORITHMS {
+        let first = generate_scheme("sunset", algorithm, None)?;
+        let second = generate_scheme("sunset", algorithm, None)?;
+        assert_eq!(first, second, "{} isn't deterministic", algorithm);
+    }
+
+    Ok(())
+}